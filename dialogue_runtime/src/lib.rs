@@ -0,0 +1,21 @@
+// Runs dialogue files made with the editor, without anything from the editor itself
+//
+// let project = Project::load_from_file("shop.json")?;
+// let mut runner = Runner::new(&project);
+// runner.start_entry("shopkeeper_greeting")?;
+// while !runner.is_finished() {
+//     match runner.current() {
+//         Current::Line { character, dialogue } => { /* show it */ runner.advance()? }
+//         Current::Choice(options) => { /* let the player pick an enabled one */ runner.choose(0)? }
+//         Current::Ended => {}
+//     }
+//     while let Some(event) = runner.pop_event() { /* handle it */ }
+// }
+// let code = runner.exit_code(); // set if it stopped at an end card with an exit code
+
+mod project;
+mod runner;
+
+pub use dialogue_maker::{Document, LoadError};
+pub use project::{BranchOption, NodeKind, Project};
+pub use runner::{Choice, Current, Event, Runner, RuntimeError};
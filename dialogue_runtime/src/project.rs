@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use dialogue_maker::{Document, LoadError, Node, NodeTypes, Operation, RandomMode, Variable};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeKind {
+    Dialogue {
+        character: String,
+        dialogue: String,
+        next: String,
+    },
+    Branches {
+        options: Vec<BranchOption>,
+    },
+    Conditional {
+        condition: String, // parsed when it's run, like the values of set variable nodes
+        if_true: String,
+        if_false: String,
+        if_not_set: String,
+    },
+    SetFlag {
+        flag_to_set: String,
+        value: bool,
+        next: String,
+    },
+    EmitEvent {
+        event: String,
+        args: Vec<(String, String)>,
+        next: String,
+    },
+    Start {
+        name: String,
+        next: String,
+    },
+    End {
+        exit_code: Option<i64>,
+    },
+    SetVariable {
+        variable: String,
+        operation: Operation,
+        value: String, // read as the variable's type when it's run
+        next: String,
+    },
+    Random {
+        mode: RandomMode,
+        outputs: Vec<(i64, String)>, // weight, next
+    },
+    Jump {
+        target: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BranchOption {
+    pub text: String,
+    pub next: String,
+    pub condition: String,   // empty when it's always shown
+    pub show_disabled: bool, // when the condition is false, instead of hiding it
+}
+
+// A project as saved by the editor, only the parts a game needs
+#[derive(Clone, Debug, Default)]
+pub struct Project {
+    nodes: HashMap<String, NodeKind>,
+    entries: HashMap<String, String>, // start name, id of the start node
+    variables: Vec<Variable>,
+}
+
+impl Project {
+    pub fn load_from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Project, LoadError> {
+        let text = std::fs::read_to_string(path)?;
+        Project::load_from_str(&text)
+    }
+
+    pub fn load_from_str(text: &str) -> Result<Project, LoadError> {
+        Ok(Project::from_document(&Document::load_from_str(text)?))
+    }
+
+    pub fn from_document(document: &Document) -> Project {
+        let mut to_return = Project::default();
+
+        for n in &document.nodes {
+            if let NodeTypes::Start = n.node_type {
+                // The last start wins when names repeat, the editor's jumps pick the same one
+                let name = n.entry_name.clone().unwrap_or_default();
+                to_return
+                    .entries
+                    .insert(name.trim().to_string(), n.id.clone());
+            }
+            to_return.nodes.insert(n.id.clone(), node_kind_from_node(n));
+        }
+        to_return.variables = document.variables.clone();
+
+        to_return
+    }
+
+    pub fn get_node(&self, id: &str) -> Option<&NodeKind> {
+        self.nodes.get(id)
+    }
+
+    pub fn node_ids(&self) -> impl Iterator<Item = &String> {
+        self.nodes.keys()
+    }
+
+    // Id of the start node with that name
+    pub fn get_entry(&self, name: &str) -> Option<&str> {
+        self.entries.get(name).map(|id| id.as_str())
+    }
+
+    pub fn entry_names(&self) -> impl Iterator<Item = &String> {
+        self.entries.keys()
+    }
+
+    // The declared variables, with the value each one starts at
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+}
+
+fn link(n: &Node, i: usize) -> String {
+    n.front_links.get(i).cloned().unwrap_or_default()
+}
+
+fn node_kind_from_node(n: &Node) -> NodeKind {
+    match n.node_type {
+        NodeTypes::Dialogue => NodeKind::Dialogue {
+            character: n.character.clone().unwrap_or_default(),
+            dialogue: n.dialogue.clone().unwrap_or_default(),
+            next: link(n, 0),
+        },
+        NodeTypes::Branches => {
+            let conditions = n.branch_conditions.clone().unwrap_or_default();
+            let show_disabled = n.branch_show_disabled.clone().unwrap_or_default();
+            NodeKind::Branches {
+                options: n
+                    .branches
+                    .clone()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, text)| BranchOption {
+                        text,
+                        next: link(n, i),
+                        condition: conditions.get(i).cloned().unwrap_or_default(),
+                        show_disabled: show_disabled.get(i).copied().unwrap_or_default(),
+                    })
+                    .collect(),
+            }
+        }
+        NodeTypes::Conditional => NodeKind::Conditional {
+            condition: n.condition.clone().unwrap_or_default(),
+            if_true: link(n, 0),
+            if_false: link(n, 1),
+            if_not_set: link(n, 2),
+        },
+        NodeTypes::SetFlag => NodeKind::SetFlag {
+            flag_to_set: n.flag_to_set.clone().unwrap_or_default(),
+            value: n.value_to_set.unwrap_or_default(),
+            next: link(n, 0),
+        },
+        NodeTypes::EmitEvent => NodeKind::EmitEvent {
+            event: n.event_to_emit.clone().unwrap_or_default(),
+            args: n.event_data.clone().unwrap_or_default(),
+            next: link(n, 0),
+        },
+        NodeTypes::Start => NodeKind::Start {
+            name: n.entry_name.clone().unwrap_or_default(),
+            next: link(n, 0),
+        },
+        NodeTypes::End => NodeKind::End {
+            exit_code: n.exit_code,
+        },
+        NodeTypes::SetVariable => NodeKind::SetVariable {
+            variable: n.variable.clone().unwrap_or_default().trim().to_string(),
+            operation: n.operation.unwrap_or(Operation::Assign),
+            value: n.variable_value.clone().unwrap_or_default(),
+            next: link(n, 0),
+        },
+        NodeTypes::Random => NodeKind::Random {
+            mode: n.random_mode.unwrap_or(RandomMode::Weighted),
+            outputs: n
+                .weights
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .enumerate()
+                .map(|(i, weight)| (weight, link(n, i)))
+                .collect(),
+        },
+        NodeTypes::Jump => NodeKind::Jump {
+            target: n.jump_target.clone().unwrap_or_default(),
+        },
+        // Document loading never makes these
+        NodeTypes::SomethingHasGoneReallyWrong => unreachable!(),
+    }
+}
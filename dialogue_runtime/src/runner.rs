@@ -0,0 +1,803 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use dialogue_maker::expression::{self, Scope};
+use dialogue_maker::{RandomMode, Value};
+
+use crate::project::{BranchOption, NodeKind, Project};
+
+// Conditionals, flags and events are run on their own, so a loop made only of them would never stop
+const MAX_AUTOMATIC_STEPS: usize = 10_000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Current {
+    Line { character: String, dialogue: String },
+    Choice(Vec<Choice>), // only the options that aren't hidden
+    Ended,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Choice {
+    pub text: String,
+    pub enabled: bool, // false for options shown disabled, they can't be chosen
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub name: String,
+    pub args: Vec<(String, String)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RuntimeError {
+    MissingNode(String),
+    MissingEntry(String),
+    InvalidVariable { id: String, reason: String }, // id of the set variable node
+    InvalidCondition { id: String, reason: String }, // id of the conditional or branches node
+    NotALine,
+    NotAChoice,
+    InvalidChoice(usize),
+    DisabledChoice(usize),
+    NothingToPick(String), // id of a random node where every weight is 0
+    EndlessLoop(String),   // id of the node where it gave up
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeError::MissingNode(id) => write!(f, "there is no node with id '{}'", id),
+            RuntimeError::MissingEntry(name) => write!(f, "there is no start called '{}'", name),
+            RuntimeError::InvalidVariable { id, reason } => {
+                write!(f, "can't set the variable in node '{}': {}", id, reason)
+            }
+            RuntimeError::InvalidCondition { id, reason } => {
+                write!(f, "can't check the condition in node '{}': {}", id, reason)
+            }
+            RuntimeError::NotALine => write!(f, "the current node is not a dialogue line"),
+            RuntimeError::NotAChoice => write!(f, "the current node is not a choice"),
+            RuntimeError::InvalidChoice(i) => write!(f, "there is no option {}", i),
+            RuntimeError::DisabledChoice(i) => write!(f, "option {} is disabled", i),
+            RuntimeError::NothingToPick(id) => {
+                write!(f, "every weight of random node '{}' is 0", id)
+            }
+            RuntimeError::EndlessLoop(id) => {
+                write!(
+                    f,
+                    "gave up after {} steps without reaching a line, at '{}'",
+                    MAX_AUTOMATIC_STEPS, id
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+pub struct Runner<'a> {
+    project: &'a Project,
+    current_id: Option<String>,
+    exit_code: Option<i64>, // from the end node the last conversation stopped at
+    flags: HashMap<String, bool>,
+    variables: HashMap<String, Value>,
+    choices: Vec<(Choice, String)>, // of the current choice, with where each one goes
+    events: VecDeque<Event>,
+
+    // Random nodes remember what they picked for as long as the runner lives, like flags
+    random_state: u64,
+    shuffle_bags: HashMap<String, (Vec<u64>, Option<usize>)>, // weights left this round, last pick
+    cycle_positions: HashMap<String, u64>,
+}
+
+impl<'a> Runner<'a> {
+    pub fn new(project: &'a Project) -> Runner<'a> {
+        Runner {
+            project,
+            current_id: None,
+            exit_code: None,
+            flags: HashMap::new(),
+            variables: project
+                .variables()
+                .iter()
+                .map(|v| (v.name.clone(), v.default.clone()))
+                .collect(),
+            choices: vec![],
+            events: VecDeque::new(),
+            random_state: time_seed(),
+            shuffle_bags: HashMap::new(),
+            cycle_positions: HashMap::new(),
+        }
+    }
+
+    // Same seed, same picks, for tests and replays
+    pub fn set_seed(&mut self, seed: u64) {
+        // Xorshift gets stuck at 0
+        self.random_state = seed.max(1);
+    }
+
+    // Flags and queued events are kept, so a runner can go through many conversations
+    pub fn start(&mut self, id: &str) -> Result<(), RuntimeError> {
+        self.exit_code = None;
+        self.go_to(id)
+    }
+
+    // Starts at the start node with that name, like "shopkeeper_greeting"
+    pub fn start_entry(&mut self, name: &str) -> Result<(), RuntimeError> {
+        match self.project.get_entry(name) {
+            Some(id) => self.start(id),
+            None => Err(RuntimeError::MissingEntry(name.to_string())),
+        }
+    }
+
+    pub fn current(&self) -> Current {
+        let id = match &self.current_id {
+            Some(id) => id,
+            None => return Current::Ended,
+        };
+
+        match self.project.get_node(id) {
+            Some(NodeKind::Dialogue {
+                character,
+                dialogue,
+                ..
+            }) => Current::Line {
+                character: character.clone(),
+                dialogue: dialogue.clone(),
+            },
+            Some(NodeKind::Branches { .. }) => {
+                Current::Choice(self.choices.iter().map(|c| c.0.clone()).collect())
+            }
+            // go_to never stops on anything else
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn current_id(&self) -> Option<&str> {
+        self.current_id.as_deref()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current_id.is_none()
+    }
+
+    // Only set once the conversation is finished, and only if it stopped at an end with a code
+    pub fn exit_code(&self) -> Option<i64> {
+        self.exit_code
+    }
+
+    // Moves past the current dialogue line
+    pub fn advance(&mut self) -> Result<(), RuntimeError> {
+        let next = match self.current_node() {
+            Some(NodeKind::Dialogue { next, .. }) => next.clone(),
+            _ => return Err(RuntimeError::NotALine),
+        };
+
+        self.go_to(&next)
+    }
+
+    // option_i counts only the options in Current::Choice, hidden ones are skipped
+    pub fn choose(&mut self, option_i: usize) -> Result<(), RuntimeError> {
+        match self.current_node() {
+            Some(NodeKind::Branches { .. }) => {}
+            _ => return Err(RuntimeError::NotAChoice),
+        }
+        let next = match self.choices.get(option_i) {
+            Some((choice, _)) if !choice.enabled => {
+                return Err(RuntimeError::DisabledChoice(option_i))
+            }
+            Some((_, next)) => next.clone(),
+            None => return Err(RuntimeError::InvalidChoice(option_i)),
+        };
+
+        self.go_to(&next)
+    }
+
+    pub fn get_flag(&self, flag: &str) -> Option<bool> {
+        self.flags.get(flag).copied()
+    }
+
+    pub fn set_flag(&mut self, flag: &str, value: bool) {
+        self.flags.insert(flag.to_string(), value);
+    }
+
+    pub fn clear_flag(&mut self, flag: &str) {
+        self.flags.remove(flag);
+    }
+
+    pub fn flags(&self) -> &HashMap<String, bool> {
+        &self.flags
+    }
+
+    // Every declared variable has a value, starting at its default
+    pub fn get_variable(&self, name: &str) -> Option<&Value> {
+        self.variables.get(name)
+    }
+
+    // For the game to change them, the type isn't checked
+    pub fn set_variable(&mut self, name: &str, value: Value) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    pub fn variables(&self) -> &HashMap<String, Value> {
+        &self.variables
+    }
+
+    // Events are queued in the order their nodes were reached
+    pub fn pop_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    fn current_node(&self) -> Option<&'a NodeKind> {
+        match &self.current_id {
+            Some(id) => self.project.get_node(id),
+            None => None,
+        }
+    }
+
+    // An empty id means the conversation is over, same as an unconnected output in the editor
+    fn go_to(&mut self, id: &str) -> Result<(), RuntimeError> {
+        let mut cur_id = id.to_string();
+
+        for _ in 0..MAX_AUTOMATIC_STEPS {
+            if cur_id.is_empty() {
+                self.current_id = None;
+                return Ok(());
+            }
+
+            let node = match self.project.get_node(&cur_id) {
+                Some(found) => found,
+                None => return Err(RuntimeError::MissingNode(cur_id)),
+            };
+
+            match node {
+                NodeKind::Dialogue { .. } => {
+                    self.current_id = Some(cur_id);
+                    return Ok(());
+                }
+                NodeKind::Branches { options } => {
+                    self.choices = self.visible_choices(&cur_id, options)?;
+                    self.current_id = Some(cur_id);
+                    return Ok(());
+                }
+                NodeKind::Conditional {
+                    condition,
+                    if_true,
+                    if_false,
+                    if_not_set,
+                } => {
+                    let result = expression::parse(condition).and_then(|c| c.eval(&*self));
+                    let result = result.map_err(|reason| RuntimeError::InvalidCondition {
+                        id: cur_id.clone(),
+                        reason,
+                    })?;
+                    cur_id = match result {
+                        Some(true) => if_true.clone(),
+                        Some(false) => if_false.clone(),
+                        None => if_not_set.clone(),
+                    };
+                }
+                NodeKind::SetFlag {
+                    flag_to_set,
+                    value,
+                    next,
+                } => {
+                    self.set_flag(flag_to_set, *value);
+                    cur_id = next.clone();
+                }
+                NodeKind::EmitEvent { event, args, next } => {
+                    self.events.push_back(Event {
+                        name: event.clone(),
+                        args: args.clone(),
+                    });
+                    cur_id = next.clone();
+                }
+                NodeKind::SetVariable {
+                    variable,
+                    operation,
+                    value,
+                    next,
+                } => {
+                    let invalid = |reason: String| RuntimeError::InvalidVariable {
+                        id: cur_id.clone(),
+                        reason,
+                    };
+                    let current = match self.variables.get(variable) {
+                        Some(current) => current,
+                        None => {
+                            return Err(invalid(format!("variable '{}' is not declared", variable)))
+                        }
+                    };
+                    let new_value = Value::parse(value, current.var_type())
+                        .and_then(|value| operation.apply(current, &value))
+                        .map_err(invalid)?;
+                    self.variables.insert(variable.clone(), new_value);
+                    cur_id = next.clone();
+                }
+                NodeKind::Start { next, .. } => {
+                    cur_id = next.clone();
+                }
+                NodeKind::Random { mode, outputs } => {
+                    cur_id = self.pick_random(&cur_id, *mode, outputs)?;
+                }
+                NodeKind::Jump { target } => {
+                    cur_id = target.clone();
+                }
+                NodeKind::End { exit_code } => {
+                    self.current_id = None;
+                    self.exit_code = *exit_code;
+                    return Ok(());
+                }
+            }
+        }
+
+        Err(RuntimeError::EndlessLoop(cur_id))
+    }
+
+    // Where a random node goes. Each output comes up as many times as its weight in every round,
+    // cycle goes through a round in order and shuffle in a random one.
+    fn pick_random(
+        &mut self,
+        id: &str,
+        mode: RandomMode,
+        outputs: &[(i64, String)],
+    ) -> Result<String, RuntimeError> {
+        let weights: Vec<u64> = outputs.iter().map(|(w, _)| (*w).max(0) as u64).collect();
+        let total = weights.iter().fold(0u64, |sum, w| sum.saturating_add(*w));
+        if total == 0 {
+            return Err(RuntimeError::NothingToPick(id.to_string()));
+        }
+
+        let picked = match mode {
+            RandomMode::Weighted => {
+                let roll = self.random_below(total);
+                weighted_index(&weights, roll)
+            }
+            RandomMode::Cycle => {
+                let position = self.cycle_positions.entry(id.to_string()).or_default();
+                let picked = weighted_index(&weights, *position % total);
+                *position = (*position + 1) % total;
+                picked
+            }
+            RandomMode::Shuffle => {
+                let (mut left, last) = self.shuffle_bags.remove(id).unwrap_or_default();
+                let refilled = left.iter().all(|w| *w == 0);
+                if refilled {
+                    left = weights;
+                }
+
+                // The last one of a round isn't the first of the next one, unless it's all there is
+                let mut can_pick = left.clone();
+                if let Some(last) = last {
+                    if refilled
+                        && can_pick
+                            .iter()
+                            .enumerate()
+                            .any(|(i, w)| i != last && *w > 0)
+                    {
+                        can_pick[last] = 0;
+                    }
+                }
+                let roll = self.random_below(
+                    can_pick
+                        .iter()
+                        .fold(0, |sum: u64, w| sum.saturating_add(*w)),
+                );
+                let picked = weighted_index(&can_pick, roll);
+
+                left[picked] -= 1;
+                self.shuffle_bags
+                    .insert(id.to_string(), (left, Some(picked)));
+                picked
+            }
+        };
+
+        Ok(outputs[picked].1.clone())
+    }
+
+    // Xorshift64*, good enough to pick lines and doesn't need a dependency
+    fn random_below(&mut self, max: u64) -> u64 {
+        let mut x = self.random_state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.random_state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D) % max
+    }
+
+    // Conditions are checked once, when the choice is reached. An option whose condition depends
+    // on a flag that isn't set counts as false.
+    fn visible_choices(
+        &self,
+        id: &str,
+        options: &[BranchOption],
+    ) -> Result<Vec<(Choice, String)>, RuntimeError> {
+        let mut to_return = vec![];
+        for option in options {
+            let enabled = match option.condition.trim().is_empty() {
+                true => true,
+                false => expression::parse(&option.condition)
+                    .and_then(|c| c.eval(self))
+                    .map_err(|reason| RuntimeError::InvalidCondition {
+                        id: id.to_string(),
+                        reason: format!("option '{}': {}", option.text, reason),
+                    })?
+                    .unwrap_or(false),
+            };
+            if !enabled && !option.show_disabled {
+                continue;
+            }
+
+            to_return.push((
+                Choice {
+                    text: option.text.clone(),
+                    enabled,
+                },
+                option.next.clone(),
+            ));
+        }
+        Ok(to_return)
+    }
+}
+
+// The output a roll below the total weight lands on
+fn weighted_index(weights: &[u64], mut roll: u64) -> usize {
+    for (i, weight) in weights.iter().enumerate() {
+        if roll < *weight {
+            return i;
+        }
+        roll -= weight;
+    }
+    unreachable!()
+}
+
+fn time_seed() -> u64 {
+    match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(since) => (since.as_nanos() as u64).max(1),
+        Err(_) => 1,
+    }
+}
+
+// Conditions see the same variables and flags the game does
+impl Scope for Runner<'_> {
+    fn variable(&self, name: &str) -> Option<Value> {
+        self.get_variable(name).cloned()
+    }
+
+    fn flag(&self, name: &str) -> Option<bool> {
+        self.get_flag(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Nodes straight in the root like the editor saves them, with one int variable "gold"
+    fn project(nodes: &str) -> Project {
+        let text = format!(
+            "{{\"version\": 10, {}, \"variables\": {{\"gold\": {{\"type\": \"int\", \"default\": 5}}}}}}",
+            nodes
+        );
+        Project::load_from_str(&text).unwrap()
+    }
+
+    // Ids of every line and choice it stops at, picking the given options in order
+    fn run(runner: &mut Runner, choices: &[usize]) -> Vec<String> {
+        let mut to_return = vec![];
+        let mut choices = choices.iter();
+        while let Some(id) = runner.current_id() {
+            to_return.push(id.to_string());
+            match runner.current() {
+                Current::Line { .. } => runner.advance().unwrap(),
+                Current::Choice(_) => runner.choose(*choices.next().unwrap()).unwrap(),
+                Current::Ended => unreachable!(),
+            }
+        }
+        to_return
+    }
+
+    fn line(id: &str, next: &str) -> String {
+        format!(
+            "\"{}\": {{\"type\": \"dialogue\", \"character\": \"A\", \"dialogue\": \"line {}\", \"next\": \"{}\"}}",
+            id, id, next
+        )
+    }
+
+    fn start(id: &str, name: &str, next: &str) -> String {
+        format!(
+            "\"{}\": {{\"type\": \"start\", \"name\": \"{}\", \"next\": \"{}\"}}",
+            id, name, next
+        )
+    }
+
+    #[test]
+    fn goes_through_lines_to_the_end() {
+        let project = project(
+            &[
+                start("00001", "intro", "00002"),
+                line("00002", "00003"),
+                line("00003", "00004"),
+                "\"00004\": {\"type\": \"end\", \"exit_code\": 3}".to_string(),
+            ]
+            .join(", "),
+        );
+        let mut runner = Runner::new(&project);
+
+        runner.start_entry("intro").unwrap();
+        assert_eq!(
+            runner.current(),
+            Current::Line {
+                character: "A".to_string(),
+                dialogue: "line 00002".to_string()
+            }
+        );
+        assert_eq!(runner.choose(0), Err(RuntimeError::NotAChoice));
+        assert_eq!(run(&mut runner, &[]), vec!["00002", "00003"]);
+        assert!(runner.is_finished());
+        assert_eq!(runner.current(), Current::Ended);
+        assert_eq!(runner.exit_code(), Some(3));
+        assert_eq!(runner.advance(), Err(RuntimeError::NotALine));
+
+        // Starting again forgets the last exit code until it gets to an end
+        runner.start("00003").unwrap();
+        assert_eq!(runner.exit_code(), None);
+        assert_eq!(
+            runner.start_entry("outro"),
+            Err(RuntimeError::MissingEntry("outro".to_string()))
+        );
+    }
+
+    #[test]
+    fn unconnected_outputs_end_and_missing_nodes_are_errors() {
+        let project = project(&[line("00001", ""), line("00002", "99999")].join(", "));
+        let mut runner = Runner::new(&project);
+
+        runner.start("00001").unwrap();
+        assert_eq!(run(&mut runner, &[]), vec!["00001"]);
+        assert_eq!(runner.exit_code(), None);
+
+        runner.start("00002").unwrap();
+        assert_eq!(
+            runner.advance(),
+            Err(RuntimeError::MissingNode("99999".to_string()))
+        );
+    }
+
+    #[test]
+    fn conditionals_take_the_true_false_or_not_set_exit() {
+        let project = project(
+            &[
+                start("00001", "check", "00002"),
+                "\"00002\": {\"type\": \"conditional\", \"condition\": \"has_key and gold > 1\", \
+                 \"if\": {\"true\": \"00003\", \"false\": \"00004\", \"not_set\": \"00005\"}}"
+                    .to_string(),
+                line("00003", ""),
+                line("00004", ""),
+                line("00005", ""),
+            ]
+            .join(", "),
+        );
+        let mut runner = Runner::new(&project);
+
+        runner.start_entry("check").unwrap();
+        assert_eq!(run(&mut runner, &[]), vec!["00005"]);
+
+        runner.set_flag("has_key", true);
+        runner.start_entry("check").unwrap();
+        assert_eq!(run(&mut runner, &[]), vec!["00003"]);
+
+        runner.set_flag("has_key", false);
+        runner.start_entry("check").unwrap();
+        assert_eq!(run(&mut runner, &[]), vec!["00004"]);
+
+        runner.clear_flag("has_key");
+        runner.start_entry("check").unwrap();
+        assert_eq!(run(&mut runner, &[]), vec!["00005"]);
+    }
+
+    #[test]
+    fn flags_variables_and_events_are_run_on_their_own() {
+        let project = project(
+            &[
+                start("00001", "intro", "00002"),
+                "\"00002\": {\"type\": \"set_flag\", \"flag_to_set\": \"met\", \"value\": true, \
+                 \"next\": \"00003\"}"
+                    .to_string(),
+                "\"00003\": {\"type\": \"emit_event\", \"event\": \"wave\", \
+                 \"args\": {\"hand\": \"left\"}, \"next\": \"00004\"}"
+                    .to_string(),
+                "\"00004\": {\"type\": \"set_variable\", \"variable\": \"gold\", \
+                 \"operation\": \"add\", \"value\": \"10\", \"next\": \"00005\"}"
+                    .to_string(),
+                "\"00005\": {\"type\": \"emit_event\", \"event\": \"pay\", \"args\": {}, \
+                 \"next\": \"00006\"}"
+                    .to_string(),
+                line("00006", ""),
+                "\"00007\": {\"type\": \"set_variable\", \"variable\": \"gold\", \
+                 \"operation\": \"assign\", \"value\": \"lots\", \"next\": \"\"}"
+                    .to_string(),
+            ]
+            .join(", "),
+        );
+        let mut runner = Runner::new(&project);
+        assert_eq!(runner.get_variable("gold"), Some(&Value::Int(5)));
+
+        runner.start_entry("intro").unwrap();
+        assert_eq!(runner.current_id(), Some("00006"));
+        assert_eq!(runner.get_flag("met"), Some(true));
+        assert_eq!(runner.get_variable("gold"), Some(&Value::Int(15)));
+        assert_eq!(
+            runner.pop_event(),
+            Some(Event {
+                name: "wave".to_string(),
+                args: vec![("hand".to_string(), "left".to_string())],
+            })
+        );
+        assert_eq!(
+            runner.pop_event(),
+            Some(Event {
+                name: "pay".to_string(),
+                args: vec![],
+            })
+        );
+        assert_eq!(runner.pop_event(), None);
+
+        assert!(matches!(
+            runner.start("00007"),
+            Err(RuntimeError::InvalidVariable { id, .. }) if id == "00007"
+        ));
+    }
+
+    #[test]
+    fn options_are_hidden_or_shown_disabled() {
+        let project = project(
+            &[
+                "\"00001\": {\"type\": \"branches\", \"branches\": [\
+                 {\"text\": \"Hi\", \"next\": \"00002\"}, \
+                 {\"text\": \"Buy\", \"next\": \"00003\", \"condition\": \"gold >= 10\"}, \
+                 {\"text\": \"Sell\", \"next\": \"00004\", \"condition\": \"gold >= 10\", \
+                 \"show_disabled\": true}, \
+                 {\"text\": \"Steal\", \"next\": \"00004\", \"condition\": \"thief\", \
+                 \"show_disabled\": true}]}"
+                    .to_string(),
+                line("00002", ""),
+                line("00003", ""),
+                line("00004", ""),
+            ]
+            .join(", "),
+        );
+        let choice = |text: &str, enabled| Choice {
+            text: text.to_string(),
+            enabled,
+        };
+        let mut runner = Runner::new(&project);
+
+        // Unset flags count as false
+        runner.start("00001").unwrap();
+        assert_eq!(
+            runner.current(),
+            Current::Choice(vec![
+                choice("Hi", true),
+                choice("Sell", false),
+                choice("Steal", false)
+            ])
+        );
+        assert_eq!(runner.choose(1), Err(RuntimeError::DisabledChoice(1)));
+        assert_eq!(runner.choose(3), Err(RuntimeError::InvalidChoice(3)));
+        assert_eq!(runner.advance(), Err(RuntimeError::NotALine));
+        assert_eq!(run(&mut runner, &[0]), vec!["00001", "00002"]);
+
+        // Checked when the choice is reached, changing gold afterwards doesn't matter
+        runner.start("00001").unwrap();
+        runner.set_variable("gold", Value::Int(10));
+        assert_eq!(runner.choose(1), Err(RuntimeError::DisabledChoice(1)));
+
+        runner.start("00001").unwrap();
+        assert_eq!(
+            runner.current(),
+            Current::Choice(vec![
+                choice("Hi", true),
+                choice("Buy", true),
+                choice("Sell", true),
+                choice("Steal", false)
+            ])
+        );
+        assert_eq!(run(&mut runner, &[2]), vec!["00001", "00004"]);
+    }
+
+    #[test]
+    fn gives_up_on_loops_without_lines() {
+        let project = project(
+            &[
+                start("00001", "loop", "00002"),
+                "\"00002\": {\"type\": \"jump\", \"target\": \"00003\"}".to_string(),
+                "\"00003\": {\"type\": \"jump\", \"target\": \"00002\"}".to_string(),
+            ]
+            .join(", "),
+        );
+        let mut runner = Runner::new(&project);
+
+        assert!(matches!(
+            runner.start_entry("loop"),
+            Err(RuntimeError::EndlessLoop(id)) if id == "00002" || id == "00003"
+        ));
+    }
+
+    fn random_project(mode: &str, weights: &[i64]) -> Project {
+        let outputs: Vec<String> = weights
+            .iter()
+            .enumerate()
+            .map(|(i, w)| format!("{{\"weight\": {}, \"next\": \"0000{}\"}}", w, i + 2))
+            .collect();
+        let mut nodes = vec![format!(
+            "\"00001\": {{\"type\": \"random\", \"mode\": \"{}\", \"outputs\": [{}]}}",
+            mode,
+            outputs.join(", ")
+        )];
+        for i in 0..weights.len() {
+            nodes.push(line(&format!("0000{}", i + 2), ""));
+        }
+        project(&nodes.join(", "))
+    }
+
+    // Where the random node went, every time it was run
+    fn picks(project: &Project, seed: u64, times: usize) -> Vec<String> {
+        let mut runner = Runner::new(project);
+        runner.set_seed(seed);
+        (0..times)
+            .map(|_| {
+                runner.start("00001").unwrap();
+                runner.current_id().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn weighted_picks_follow_the_weights() {
+        let project = random_project("weighted", &[3, 0, 1]);
+        let picked = picks(&project, 7, 400);
+
+        assert_eq!(picked, picks(&project, 7, 400));
+        assert_ne!(picked, picks(&project, 8, 400));
+        assert!(!picked.iter().any(|id| id == "00003"));
+        let firsts = picked.iter().filter(|id| *id == "00002").count();
+        assert!(firsts > 250 && firsts < 350, "{}", firsts);
+
+        let nothing = random_project("weighted", &[0, 0]);
+        let mut runner = Runner::new(&nothing);
+        assert_eq!(
+            runner.start("00001"),
+            Err(RuntimeError::NothingToPick("00001".to_string()))
+        );
+    }
+
+    #[test]
+    fn cycle_goes_through_the_outputs_in_order() {
+        let project = random_project("cycle", &[2, 0, 1]);
+        assert_eq!(
+            picks(&project, 1, 7),
+            vec!["00002", "00002", "00004", "00002", "00002", "00004", "00002"]
+        );
+    }
+
+    #[test]
+    fn shuffle_uses_every_output_once_per_round() {
+        let project = random_project("shuffle", &[2, 1, 1]);
+        for seed in 1..50 {
+            let picked = picks(&project, seed, 40);
+            for round in picked.chunks(4) {
+                let mut round = round.to_vec();
+                round.sort();
+                assert_eq!(round, vec!["00002", "00002", "00003", "00004"]);
+            }
+            for i in (4..40).step_by(4) {
+                assert_ne!(picked[i - 1], picked[i], "seed {} repeats at {}", seed, i);
+            }
+        }
+
+        // With only one output there's nothing else to pick
+        assert_eq!(
+            picks(&random_project("shuffle", &[2]), 3, 4),
+            vec!["00002"; 4]
+        );
+    }
+}
@@ -0,0 +1,74 @@
+// Run with: cargo bench -p json_parser
+// Parses generated dialogue graphs of growing size, the time per node should stay about the same
+
+use std::time::Instant;
+
+use json_parser::{JsonObject, JsonType, Parser};
+
+// Same layout the editor writes when saving
+fn generate_dialogue_graph(node_count: usize) -> String {
+    let mut obj = JsonObject::new();
+    obj.set_integer("version", 10);
+
+    for i in 1..=node_count {
+        let id = format!("{:0>5}", i);
+        let next = format!("{:0>5}", i + 1);
+
+        let mut sub_obj = JsonObject::new();
+        if i % 3 == 0 {
+            sub_obj.set_string("type", "branches");
+            let mut exits = vec![];
+            for (text, exit_next) in [("Sure, \"why not\"", next.as_str()), ("No.\nNever.", "")] {
+                let mut exit = JsonObject::new();
+                exit.set_string("text", text);
+                exit.set_string("next", exit_next);
+                exits.push(JsonType::Object(exit));
+            }
+            sub_obj.set_array("branches", exits);
+        } else {
+            sub_obj.set_string("type", "dialogue");
+            sub_obj.set_string("character", "Shopkeeper");
+            sub_obj.set_string(
+                "dialogue",
+                "Welcome, traveler! Take a look at my wares, ünd tell me if anything catches your eye.",
+            );
+            sub_obj.set_string("next", &next);
+        }
+        obj.children.push((id, JsonType::Object(sub_obj)));
+    }
+
+    obj.push_obj("editor");
+    let cards_obj = obj.get_obj_ref("editor").unwrap();
+    for i in 1..=node_count {
+        let id = format!("{:0>5}", i);
+        cards_obj.push_obj(&id);
+        let card_obj = cards_obj.get_obj_ref(&id).unwrap();
+        card_obj.set_number("x", i as f64 * 200.);
+        card_obj.set_number("y", -(i as f64) * 12.5);
+    }
+
+    obj.stringify()
+}
+
+fn main() {
+    for node_count in [1_000, 5_000, 20_000] {
+        let text = generate_dialogue_graph(node_count);
+        let runs = 10;
+
+        let start = Instant::now();
+        for _ in 0..runs {
+            let mut parser = Parser::new();
+            parser.load(&text);
+            parser.parse().unwrap();
+        }
+        let per_run = start.elapsed() / runs;
+
+        println!(
+            "{:>6} nodes ({:>8} bytes): {:>10.3?} per parse, {:>8.3?} per node",
+            node_count,
+            text.len(),
+            per_run,
+            per_run / node_count as u32
+        );
+    }
+}
@@ -0,0 +1,1129 @@
+// V 2
+// https://github.com/JoaoHenriqueProg/j_json_parser
+
+use std::fmt;
+
+pub struct Parser {
+    cur_text: String,
+    cur_i: usize,
+    keep_integers: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonObject {
+    pub children: Vec<(String, JsonType)>,
+}
+
+impl Default for JsonObject {
+    fn default() -> JsonObject {
+        JsonObject::new()
+    }
+}
+
+impl JsonObject {
+    pub fn new() -> JsonObject {
+        JsonObject {
+            children: Vec::new(),
+        }
+    }
+
+    pub fn print(&self) {
+        println!("{}", self.stringify());
+    }
+
+    pub fn stringify(&self) -> String {
+        self.priv_stringify(0)
+    }
+
+    fn priv_stringify(&self, indent: u8) -> String {
+        if self.children.is_empty() {
+            return "{}".to_string();
+        }
+
+        let mut to_return: String = "{".to_string();
+
+        for (i, to_spit) in self.children.iter().enumerate() {
+            if i != 0 {
+                to_return.push(',');
+            }
+            to_return.push('\n');
+            push_indent(&mut to_return, indent + 1);
+
+            to_return.push_str(&format!(
+                "\"{}\": {}",
+                escape_string(&to_spit.0),
+                stringify_value(&to_spit.1, indent + 1)
+            ));
+        }
+
+        to_return.push('\n');
+        push_indent(&mut to_return, indent);
+
+        to_return.push('}');
+        to_return
+    }
+
+    fn get_index_of_key<T: ToString>(&self, key: T) -> i64 {
+        let key = key.to_string();
+        for (i, child) in self.children.iter().enumerate() {
+            if child.0 == key {
+                return i as i64;
+            }
+        }
+        -1
+    }
+
+    pub fn get<T: ToString>(&self, key: T) -> Result<JsonType, JsonError> {
+        let i = self.get_index_of_key(key.to_string());
+
+        if i == -1 {
+            Err(JsonError::KeyNotFound)
+        } else {
+            Ok(self.children[i as usize].1.clone())
+        }
+    }
+
+    pub fn get_bool<T: ToString>(&self, key: T) -> Result<bool, JsonError> {
+        let i = self.get_index_of_key(key.to_string());
+
+        if i == -1 {
+            return Err(JsonError::KeyNotFound);
+        }
+        match self.children[i as usize].1 {
+            JsonType::Bool(val) => Ok(val),
+            _ => Err(JsonError::WrongTypeValueRequest),
+        }
+    }
+
+    pub fn get_number<T: ToString>(&self, key: T) -> Result<f64, JsonError> {
+        let i = self.get_index_of_key(key.to_string());
+
+        if i == -1 {
+            return Err(JsonError::KeyNotFound);
+        }
+        match self.children[i as usize].1 {
+            JsonType::Number(val) => Ok(val),
+            JsonType::Integer(val) => Ok(val as f64),
+            _ => Err(JsonError::WrongTypeValueRequest),
+        }
+    }
+
+    pub fn get_integer<T: ToString>(&self, key: T) -> Result<i64, JsonError> {
+        let i = self.get_index_of_key(key.to_string());
+
+        if i == -1 {
+            return Err(JsonError::KeyNotFound);
+        }
+        match self.children[i as usize].1 {
+            JsonType::Integer(val) => Ok(val),
+            // Parsers that don't keep integers still give whole numbers as floats
+            JsonType::Number(val) if val.fract() == 0. && val.abs() < i64::MAX as f64 => {
+                Ok(val as i64)
+            }
+            _ => Err(JsonError::WrongTypeValueRequest),
+        }
+    }
+
+    pub fn get_string<T: ToString>(&self, key: T) -> Result<String, JsonError> {
+        let i = self.get_index_of_key(key.to_string());
+
+        if i == -1 {
+            return Err(JsonError::KeyNotFound);
+        }
+        match &self.children[i as usize].1 {
+            JsonType::String(val) => Ok(val.clone()),
+            _ => Err(JsonError::WrongTypeValueRequest),
+        }
+    }
+
+    pub fn get_array<T: ToString>(&self, key: T) -> Result<Vec<JsonType>, JsonError> {
+        let i = self.get_index_of_key(key.to_string());
+
+        if i == -1 {
+            return Err(JsonError::KeyNotFound);
+        }
+        match &self.children[i as usize].1 {
+            JsonType::Array(val) => Ok(val.clone()),
+            _ => Err(JsonError::WrongTypeValueRequest),
+        }
+    }
+
+    pub fn get_obj<T: ToString>(&self, key: T) -> Result<JsonObject, JsonError> {
+        let i = self.get_index_of_key(key.to_string());
+
+        if i == -1 {
+            return Err(JsonError::KeyNotFound);
+        }
+        match &self.children[i as usize].1 {
+            JsonType::Object(val) => Ok(val.clone()),
+            _ => Err(JsonError::WrongTypeValueRequest),
+        }
+    }
+
+    pub fn get_obj_ref<T: ToString>(&mut self, key: T) -> Result<&mut JsonObject, JsonError> {
+        let i = self.get_index_of_key(key.to_string());
+
+        if i == -1 {
+            return Err(JsonError::KeyNotFound);
+        }
+        match &mut self.children[i as usize].1 {
+            JsonType::Object(val) => Ok(val),
+            _ => Err(JsonError::WrongTypeValueRequest),
+        }
+    }
+
+    pub fn set_bool<T: ToString>(&mut self, new_key: T, new_value: bool) {
+        let to_add = (new_key.to_string(), JsonType::Bool(new_value));
+        let i = self.get_index_of_key(new_key);
+
+        if i == -1 {
+            self.children.push(to_add);
+        } else {
+            self.children[i as usize] = to_add;
+        }
+    }
+    pub fn set_number<T: ToString>(&mut self, new_key: T, new_value: f64) {
+        let to_add = (new_key.to_string(), JsonType::Number(new_value));
+        let i = self.get_index_of_key(new_key);
+
+        if i == -1 {
+            self.children.push(to_add);
+        } else {
+            self.children[i as usize] = to_add;
+        }
+    }
+    pub fn set_integer<T: ToString>(&mut self, new_key: T, new_value: i64) {
+        let to_add = (new_key.to_string(), JsonType::Integer(new_value));
+        let i = self.get_index_of_key(new_key);
+
+        if i == -1 {
+            self.children.push(to_add);
+        } else {
+            self.children[i as usize] = to_add;
+        }
+    }
+    pub fn set_string<T: ToString>(&mut self, new_key: T, new_value: T) {
+        let to_add = (new_key.to_string(), JsonType::String(new_value.to_string()));
+        let i = self.get_index_of_key(new_key);
+
+        if i == -1 {
+            self.children.push(to_add);
+        } else {
+            self.children[i as usize] = to_add;
+        }
+    }
+    pub fn set_array<T: ToString>(&mut self, new_key: T, new_value: Vec<JsonType>) {
+        let to_add = (new_key.to_string(), JsonType::Array(new_value));
+        let i = self.get_index_of_key(new_key);
+
+        if i == -1 {
+            self.children.push(to_add);
+        } else {
+            self.children[i as usize] = to_add;
+        }
+    }
+    pub fn set_null<T: ToString>(&mut self, new_key: T) {
+        let to_add = (new_key.to_string(), JsonType::Null);
+        let i = self.get_index_of_key(new_key);
+
+        if i == -1 {
+            self.children.push(to_add);
+        } else {
+            self.children[i as usize] = to_add;
+        }
+    }
+
+    pub fn push_obj<T: ToString>(&mut self, new_key: T) {
+        let to_add = (new_key.to_string(), JsonType::Object(JsonObject::new()));
+        let i = self.get_index_of_key(new_key);
+
+        if i == -1 {
+            self.children.push(to_add);
+        } else {
+            self.children[i as usize] = to_add;
+        }
+    }
+}
+
+fn push_indent(to_push_to: &mut String, indent: u8) {
+    for _ in 0..indent as usize * 2 {
+        to_push_to.push(' ');
+    }
+}
+
+fn stringify_value(to_stringify: &JsonType, indent: u8) -> String {
+    match to_stringify {
+        JsonType::Object(val) => val.priv_stringify(indent),
+        JsonType::Bool(val) => val.to_string(),
+        JsonType::Number(val) => stringify_number(*val),
+        JsonType::Integer(val) => val.to_string(),
+        JsonType::String(val) => format!("\"{}\"", escape_string(val)),
+        JsonType::Array(val) => stringify_array(val, indent),
+        JsonType::Null => "null".to_string(),
+    }
+}
+
+fn stringify_array(array_to_stringify: &[JsonType], indent: u8) -> String {
+    if array_to_stringify.is_empty() {
+        return "[]".to_string();
+    }
+
+    let mut to_return: String = "[".to_string();
+
+    for (i, to_spit) in array_to_stringify.iter().enumerate() {
+        if i != 0 {
+            to_return.push(',');
+        }
+        to_return.push('\n');
+        push_indent(&mut to_return, indent + 1);
+
+        to_return.push_str(&stringify_value(to_spit, indent + 1));
+    }
+
+    to_return.push('\n');
+    push_indent(&mut to_return, indent);
+
+    to_return.push(']');
+    to_return
+}
+
+// JSON has no NaN or infinity, null is the closest thing to them
+fn stringify_number(val: f64) -> String {
+    if !val.is_finite() {
+        return "null".to_string();
+    }
+    // Whole floats keep a fraction, otherwise they'd come back as integers with keep_integers
+    let mut to_return = val.to_string();
+    if !to_return.contains('.') {
+        to_return.push_str(".0");
+    }
+    to_return
+}
+
+// Escapes everything RFC 8259 requires, anything else is written as is
+fn escape_string(to_escape: &str) -> String {
+    let mut to_return = String::with_capacity(to_escape.len());
+
+    for c in to_escape.chars() {
+        match c {
+            '"' => to_return.push_str("\\\""),
+            '\\' => to_return.push_str("\\\\"),
+            '\n' => to_return.push_str("\\n"),
+            '\r' => to_return.push_str("\\r"),
+            '\t' => to_return.push_str("\\t"),
+            '\u{8}' => to_return.push_str("\\b"),
+            '\u{c}' => to_return.push_str("\\f"),
+            c if (c as u32) < 0x20 => to_return.push_str(&format!("\\u{:04x}", c as u32)),
+            c => to_return.push(c),
+        }
+    }
+
+    to_return
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonType {
+    Bool(bool),
+    Number(f64),
+    Integer(i64), // Only produced by the parser when keep_integers is set
+    String(String),
+    Array(Vec<JsonType>),
+    Object(JsonObject),
+    Null,
+}
+
+#[derive(Debug)]
+pub enum JsonError {
+    KeyNotFound,
+    WrongTypeValueRequest,
+    Parse(ParseError),
+}
+
+impl From<ParseError> for JsonError {
+    fn from(err: ParseError) -> JsonError {
+        JsonError::Parse(err)
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonError::KeyNotFound => write!(f, "key not found"),
+            JsonError::WrongTypeValueRequest => write!(f, "value has a different type"),
+            JsonError::Parse(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+// line and column start at 1, offset is in bytes from the start of the text
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+    pub expected: String,
+    pub found: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: expected {} but found {}",
+            self.line, self.column, self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Default for Parser {
+    fn default() -> Parser {
+        Parser::new()
+    }
+}
+
+impl Parser {
+    pub fn new() -> Parser {
+        Parser {
+            cur_text: "".to_string(),
+            cur_i: 0,
+            keep_integers: false,
+        }
+    }
+
+    // When set, numbers without a fraction or exponent are parsed into JsonType::Integer
+    pub fn set_keep_integers(&mut self, keep: bool) {
+        self.keep_integers = keep;
+    }
+
+    // cur_i is a byte index, so looking at the current char doesn't need to walk the whole text
+    fn cur_char(&self) -> Option<char> {
+        self.cur_text[self.cur_i..].chars().next()
+    }
+
+    fn get_substr(&mut self, len: usize) -> String {
+        let to_return: String = self.cur_text[self.cur_i..].chars().take(len).collect();
+
+        self.cur_i += to_return.len();
+
+        to_return
+    }
+
+    fn error_at<T: ToString, U: ToString>(
+        &self,
+        offset: usize,
+        expected: T,
+        found: U,
+    ) -> ParseError {
+        let mut line = 1;
+        let mut column = 1;
+
+        for c in self.cur_text[..offset].chars() {
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+
+        ParseError {
+            line,
+            column,
+            offset,
+            expected: expected.to_string(),
+            found: found.to_string(),
+        }
+    }
+
+    fn error<T: ToString>(&self, expected: T) -> ParseError {
+        let found = match self.cur_char() {
+            Some(c) => format!("{:?}", c),
+            None => "end of input".to_string(),
+        };
+
+        self.error_at(self.cur_i, expected, found)
+    }
+
+    fn expect_char(&self, to_expect: char) -> Result<(), ParseError> {
+        if self.cur_char() != Some(to_expect) {
+            return Err(self.error(format!("'{}'", to_expect)));
+        }
+        Ok(())
+    }
+
+    fn ignore_white_space(&mut self) {
+        while let Some(' ' | '\n' | '\t' | '\r') = self.cur_char() {
+            self.cur_i += 1;
+        }
+    }
+
+    pub fn load<T: ToString>(&mut self, new_text: T) {
+        self.cur_text = new_text.to_string();
+        self.cur_i = 0;
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        self.expect_char('"')?;
+
+        self.cur_i += 1;
+
+        let mut to_return = "".to_string();
+
+        if self.cur_char() == Some('"') {
+            self.cur_i += 1;
+            return Ok(to_return);
+        }
+
+        loop {
+            // Copy everything up to the next special character in one go
+            let run_len = self.cur_text.as_bytes()[self.cur_i..]
+                .iter()
+                .position(|b| *b == b'"' || *b == b'\\' || *b < 0x20)
+                .unwrap_or(self.cur_text.len() - self.cur_i);
+            to_return.push_str(&self.cur_text[self.cur_i..self.cur_i + run_len]);
+            self.cur_i += run_len;
+
+            match self.cur_char() {
+                Some('"') => break,
+                Some('\\') => {
+                    self.cur_i += 1;
+                    to_return.push(self.parse_escape()?);
+                }
+                Some(_) => {
+                    return Err(self.error("an escaped control character"));
+                }
+                None => return Err(self.error("closing '\"'")),
+            }
+        }
+
+        self.cur_i += 1;
+        self.ignore_white_space();
+
+        Ok(to_return)
+    }
+
+    // Called right after the '\\', leaves cur_i after the whole escape sequence
+    fn parse_escape(&mut self) -> Result<char, ParseError> {
+        let to_return = match self.cur_char() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let start_i = self.cur_i - 1;
+                self.cur_i += 1;
+                let high = self.parse_hex_code_unit()?;
+
+                let code_point = match high {
+                    0xD800..=0xDBFF => {
+                        // High surrogate, the low one has to come right after
+                        if self.get_substr(2) != "\\u" {
+                            return Err(self.error_at(
+                                start_i,
+                                "a low surrogate after a high surrogate",
+                                "a lone high surrogate",
+                            ));
+                        }
+                        let low = self.parse_hex_code_unit()?;
+                        if !(0xDC00..=0xDFFF).contains(&low) {
+                            return Err(self.error_at(
+                                start_i,
+                                "a low surrogate after a high surrogate",
+                                format!("\\u{:04X}", low),
+                            ));
+                        }
+                        0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                    }
+                    0xDC00..=0xDFFF => {
+                        return Err(self.error_at(
+                            start_i,
+                            "a high surrogate before a low surrogate",
+                            "a lone low surrogate",
+                        ));
+                    }
+                    _ => high,
+                };
+
+                // Surrogates were handled above, so this is always a valid char
+                return Ok(char::from_u32(code_point).unwrap());
+            }
+            _ => return Err(self.error("a valid escape character")),
+        };
+
+        self.cur_i += 1;
+        Ok(to_return)
+    }
+
+    fn parse_hex_code_unit(&mut self) -> Result<u32, ParseError> {
+        let start_i = self.cur_i;
+        let digits = self.get_substr(4);
+
+        if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(self.error_at(start_i, "4 hex digits", format!("'{}'", digits)));
+        }
+        Ok(u32::from_str_radix(&digits, 16).unwrap())
+    }
+
+    fn parse_bool(&mut self) -> Result<bool, ParseError> {
+        let start_i = self.cur_i;
+        let keyword_len = if self.cur_char() == Some('f') { 5 } else { 4 };
+
+        let chars = self.get_substr(keyword_len);
+
+        if chars == "true" {
+            Ok(true)
+        } else if chars == "false" {
+            Ok(false)
+        } else {
+            Err(self.error_at(start_i, "true or false", format!("'{}'", chars)))
+        }
+    }
+
+    fn parse_null(&mut self) -> Result<(), ParseError> {
+        let start_i = self.cur_i;
+        let chars = self.get_substr(4);
+
+        if chars == "null" {
+            return Ok(());
+        }
+        Err(self.error_at(start_i, "null", format!("'{}'", chars)))
+    }
+
+    fn skip_digits(&mut self, stringed_number: &mut String) -> usize {
+        let mut count = 0;
+
+        while let Some(c @ '0'..='9') = self.cur_char() {
+            stringed_number.push(c);
+            self.cur_i += 1;
+            count += 1;
+        }
+
+        count
+    }
+
+    // number = [ "-" ] ( "0" / digit1-9 *digit ) [ "." 1*digit ] [ ( "e" / "E" ) [ "+" / "-" ] 1*digit ]
+    fn parse_number(&mut self) -> Result<JsonType, ParseError> {
+        let mut stringed_number = "".to_string();
+        let mut is_integer = true;
+
+        if self.cur_char() == Some('-') {
+            stringed_number.push('-');
+            self.cur_i += 1;
+        }
+
+        match self.cur_char() {
+            Some('0') => {
+                stringed_number.push('0');
+                self.cur_i += 1;
+
+                if let Some('0'..='9') = self.cur_char() {
+                    return Err(self.error("a number without leading zeros"));
+                }
+            }
+            Some('1'..='9') => {
+                self.skip_digits(&mut stringed_number);
+            }
+            _ => return Err(self.error("a digit")),
+        }
+
+        if self.cur_char() == Some('.') {
+            is_integer = false;
+            stringed_number.push('.');
+            self.cur_i += 1;
+
+            if self.skip_digits(&mut stringed_number) == 0 {
+                return Err(self.error("a digit after '.'"));
+            }
+        }
+
+        if let Some('e' | 'E') = self.cur_char() {
+            is_integer = false;
+            stringed_number.push('e');
+            self.cur_i += 1;
+
+            if let Some(c @ ('+' | '-')) = self.cur_char() {
+                stringed_number.push(c);
+                self.cur_i += 1;
+            }
+
+            if self.skip_digits(&mut stringed_number) == 0 {
+                return Err(self.error("a digit in the exponent"));
+            }
+        }
+
+        if is_integer && self.keep_integers {
+            // Too big for an i64, so it falls back to a float like every other number
+            if let Ok(val) = stringed_number.parse::<i64>() {
+                return Ok(JsonType::Integer(val));
+            }
+        }
+
+        // The grammar was already checked above, so this can't fail
+        Ok(JsonType::Number(stringed_number.parse().unwrap()))
+    }
+
+    fn parse_value(&mut self) -> Result<JsonType, ParseError> {
+        match self.cur_char() {
+            Some('t' | 'f') => Ok(JsonType::Bool(self.parse_bool()?)),
+            Some('0'..='9' | '-') => self.parse_number(),
+            Some('"') => Ok(JsonType::String(self.parse_string()?)),
+            Some('[') => Ok(JsonType::Array(self.parse_array()?)),
+            Some('{') => Ok(JsonType::Object(self.parse_object()?)),
+            Some('n') => {
+                self.parse_null()?;
+                Ok(JsonType::Null)
+            }
+            _ => Err(self.error("a value")),
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Vec<JsonType>, ParseError> {
+        let mut to_return: Vec<JsonType> = Vec::new();
+
+        self.expect_char('[')?;
+
+        self.cur_i += 1;
+        self.ignore_white_space();
+
+        if self.cur_char() == Some(']') {
+            self.cur_i += 1;
+            return Ok(to_return);
+        }
+
+        loop {
+            self.ignore_white_space();
+
+            to_return.push(self.parse_value()?);
+
+            if self.ends_after_value(']')? {
+                break;
+            }
+        }
+
+        Ok(to_return)
+    }
+
+    // Reads what comes after a value in an array or object, true when it was the closing char
+    fn ends_after_value(&mut self, close: char) -> Result<bool, ParseError> {
+        self.ignore_white_space();
+
+        if self.cur_char() == Some(close) {
+            self.cur_i += 1;
+            return Ok(true);
+        }
+
+        self.expect_char(',')
+            .map_err(|_| self.error(format!("',' or '{}'", close)))?;
+        self.cur_i += 1;
+
+        self.ignore_white_space();
+
+        // Not valid JSON, but the writer used to put a comma after every value, so files
+        // saved back then are still read on purpose. Nothing is written this way anymore.
+        if self.cur_char() == Some(close) {
+            self.cur_i += 1;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    fn parse_object(&mut self) -> Result<JsonObject, ParseError> {
+        let mut to_return: JsonObject = JsonObject::new();
+
+        self.expect_char('{')?;
+
+        self.cur_i += 1;
+        self.ignore_white_space();
+
+        if self.cur_char() == Some('}') {
+            self.cur_i += 1;
+            return Ok(to_return);
+        }
+
+        loop {
+            self.ignore_white_space();
+
+            let new_key = self.parse_string()?;
+            // Aparently, this is allowed
+            // if new_key == "" {
+            //     panic!("Empty key!");
+            // }
+
+            self.ignore_white_space();
+            self.expect_char(':')?;
+            self.cur_i += 1;
+            self.ignore_white_space();
+
+            let result = self.parse_value()?;
+            to_return.children.push((new_key, result));
+
+            if self.ends_after_value('}')? {
+                break;
+            }
+        }
+
+        Ok(to_return)
+    }
+
+    pub fn parse(&mut self) -> Result<JsonObject, ParseError> {
+        if self.cur_i != 0 {
+            return Err(self.error("a newly loaded json text"));
+        }
+
+        self.ignore_white_space();
+        let to_return = self.parse_object()?;
+
+        self.ignore_white_space();
+        if self.cur_char().is_some() {
+            return Err(self.error("end of input"));
+        }
+
+        Ok(to_return)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<JsonObject, ParseError> {
+        let mut parser = Parser::new();
+        parser.set_keep_integers(true);
+        parser.load(text);
+        parser.parse()
+    }
+
+    // The string as it's written between the quotes of a saved file
+    fn escaped(text: &str) -> String {
+        let mut obj = JsonObject::new();
+        obj.set_string("s", text);
+        let written = obj.stringify();
+        let start = written.find(": \"").unwrap() + 3;
+        written[start..written.len() - 3].to_string()
+    }
+
+    fn unescape(escaped: &str) -> Result<String, ParseError> {
+        Ok(parse(&format!("{{\"s\": \"{}\"}}", escaped))?
+            .get_string("s")
+            .unwrap())
+    }
+
+    #[test]
+    fn escapes_exactly_what_rfc_8259_requires() {
+        assert_eq!(escaped("say \"hi\""), "say \\\"hi\\\"");
+        assert_eq!(escaped("a\\b"), "a\\\\b");
+        assert_eq!(escaped("\n\r\t\u{8}\u{c}"), "\\n\\r\\t\\b\\f");
+        assert_eq!(escaped("\u{0}\u{1}\u{1f}"), "\\u0000\\u0001\\u001f");
+        // Everything else is written as is, slashes and non ascii included
+        assert_eq!(escaped("/ \u{7f} é ツ 😀"), "/ \u{7f} é ツ 😀");
+    }
+
+    #[test]
+    fn unescapes_every_escape() {
+        assert_eq!(
+            unescape("\\\" \\\\ \\/ \\b \\f \\n \\r \\t").unwrap(),
+            "\" \\ / \u{8} \u{c} \n \r \t"
+        );
+        assert_eq!(unescape("\\u0041\\u00e9\\u30C4").unwrap(), "Aéツ");
+        assert_eq!(unescape("\\u0000\\u001F").unwrap(), "\u{0}\u{1f}");
+        assert_eq!(unescape("\\ud83d\\ude00").unwrap(), "😀");
+        assert_eq!(unescape("\\uD834\\uDD1E").unwrap(), "\u{1d11e}");
+    }
+
+    #[test]
+    fn rejects_bad_escapes() {
+        let bad = [
+            "\\ud83d",        // lone high surrogate at the end
+            "\\ud83d abc",    // lone high surrogate in the middle
+            "\\ud83d\\u0041", // high surrogate followed by something that isn't a low one
+            "\\ud83d\\ud83d", // two high surrogates
+            "\\ude00",        // lone low surrogate
+            "\\ude00\\ud83d", // surrogates the wrong way around
+            "\\u12g4",        // not hex
+            "\\u12",          // too short
+            "\\x",            // not an escape
+            "\\",             // nothing after the backslash
+            "tab\there",      // control characters have to be escaped
+            "new\nline",
+        ];
+        for text in bad {
+            assert!(unescape(text).is_err(), "{:?} should be rejected", text);
+        }
+    }
+
+    // Small xorshift so the generated values are the same on every run
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, max: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % max
+        }
+    }
+
+    fn random_string(rng: &mut Rng) -> String {
+        let chars = [
+            'a', 'Z', ' ', '"', '\\', '/', '\n', '\t', '\u{1}', 'é', 'ツ', '😀',
+        ];
+        (0..rng.below(6))
+            .map(|_| chars[rng.below(chars.len() as u64) as usize])
+            .collect()
+    }
+
+    fn random_value(rng: &mut Rng, depth: u32) -> JsonType {
+        let kinds = if depth == 0 { 5 } else { 7 };
+        match rng.below(kinds) {
+            0 => JsonType::Null,
+            1 => JsonType::Bool(rng.below(2) == 0),
+            2 => JsonType::Integer(rng.below(u64::MAX) as i64),
+            3 => {
+                let val = match rng.below(4) {
+                    0 => rng.below(1000) as f64, // whole floats have to stay floats
+                    1 => -(rng.below(1 << 40) as f64) / 1024.,
+                    2 => f64::from_bits(rng.below(u64::MAX)) % 1e300,
+                    _ => 0.1,
+                };
+                JsonType::Number(if val.is_finite() { val } else { 0.5 })
+            }
+            4 => JsonType::String(random_string(rng)),
+            5 => JsonType::Array(
+                (0..rng.below(4))
+                    .map(|_| random_value(rng, depth - 1))
+                    .collect(),
+            ),
+            _ => JsonType::Object(random_object(rng, depth - 1)),
+        }
+    }
+
+    fn random_object(rng: &mut Rng, depth: u32) -> JsonObject {
+        let mut to_return = JsonObject::new();
+        for i in 0..rng.below(4) {
+            let key = format!("{}{}", random_string(rng), i); // keys have to be different
+            to_return.children.push((key, random_value(rng, depth)));
+        }
+        to_return
+    }
+
+    fn has_trailing_comma(text: &str) -> bool {
+        let mut after_comma = false;
+        let mut in_string = false;
+        let mut escaped = false;
+        for c in text.chars() {
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                ']' | '}' if after_comma => return true,
+                ',' => after_comma = true,
+                '"' => {
+                    in_string = true;
+                    after_comma = false;
+                }
+                c if c.is_whitespace() => {}
+                _ => after_comma = false,
+            }
+        }
+        false
+    }
+
+    fn assert_round_trips(obj: &JsonObject) {
+        let text = obj.stringify();
+        assert!(!has_trailing_comma(&text), "trailing comma in:\n{}", text);
+        assert_eq!(&parse(&text).unwrap(), obj, "didn't round trip:\n{}", text);
+    }
+
+    #[test]
+    fn every_value_type_round_trips() {
+        let mut nested = JsonObject::new();
+        nested.set_array("empty array", vec![]);
+        nested.children.push((
+            "empty object".to_string(),
+            JsonType::Object(JsonObject::new()),
+        ));
+        nested.set_null("nothing");
+
+        let mut obj = JsonObject::new();
+        obj.set_array(
+            "strings",
+            vec![
+                JsonType::String("one".to_string()),
+                JsonType::String("".to_string()),
+                JsonType::String("\"quoted\"".to_string()),
+            ],
+        );
+        obj.set_array("nulls", vec![JsonType::Null, JsonType::Null]);
+        obj.set_array(
+            "nested",
+            vec![
+                JsonType::Array(vec![JsonType::Array(vec![]), JsonType::Integer(1)]),
+                JsonType::Object(nested.clone()),
+                JsonType::Bool(false),
+            ],
+        );
+        obj.children
+            .push(("object".to_string(), JsonType::Object(nested)));
+        obj.set_integer("integer", -42);
+        obj.set_integer("big integer", i64::MAX);
+        obj.set_number("float", 2.5);
+        obj.set_number("whole float", 3.);
+        obj.set_number("tiny float", 1e-300);
+        obj.set_bool("bool", true);
+
+        assert_round_trips(&obj);
+        assert_round_trips(&JsonObject::new());
+    }
+
+    #[test]
+    fn random_documents_round_trip() {
+        let mut rng = Rng(0x2545F4914F6CDD1D);
+        for _ in 0..500 {
+            assert_round_trips(&random_object(&mut rng, 3));
+        }
+    }
+
+    #[test]
+    fn trailing_commas_are_still_read() {
+        let old = "{\"a\": [1, 2,], \"b\": {\"c\": null,},}";
+        let obj = parse(old).unwrap();
+        assert_eq!(
+            obj.get_array("a").unwrap(),
+            vec![JsonType::Integer(1), JsonType::Integer(2)]
+        );
+        assert_eq!(obj.get_obj("b").unwrap().get("c").unwrap(), JsonType::Null);
+        assert!(!has_trailing_comma(&obj.stringify()));
+        assert!(has_trailing_comma(old));
+    }
+
+    fn error(text: &str) -> ParseError {
+        parse(text).unwrap_err()
+    }
+
+    #[test]
+    fn errors_point_at_the_line_and_column() {
+        let err = error("{\"a\": 1,\n \"b\": x}");
+        assert_eq!((err.line, err.column, err.offset), (2, 7, 15));
+        assert_eq!(
+            err.to_string(),
+            "line 2, column 7: expected a value but found 'x'"
+        );
+
+        let err = error("{\"a\": 1,\r\n\n\t\"b\" 2}");
+        assert_eq!((err.line, err.column, err.offset), (3, 6, 16));
+        assert_eq!((err.expected.as_str(), err.found.as_str()), ("':'", "'2'"));
+    }
+
+    #[test]
+    fn columns_count_characters_not_bytes() {
+        // é is 2 bytes and 😀 is 4, the offset stays in bytes
+        let err = error("{\"é😀\": x}");
+        assert_eq!((err.line, err.column, err.offset), (1, 8, 11));
+
+        let err = error("{\"a\": \"ツ\",\n\"😀\": [1 2]}");
+        assert_eq!((err.line, err.column, err.offset), (2, 9, 24));
+
+        let err = error("{\"ツ\": 1");
+        assert_eq!((err.line, err.column, err.offset), (1, 8, 9));
+        assert_eq!(err.found, "end of input");
+    }
+
+    fn number(text: &str, keep_integers: bool) -> Result<JsonType, ParseError> {
+        let mut parser = Parser::new();
+        parser.set_keep_integers(keep_integers);
+        parser.load(format!("{{\"n\": {}}}", text));
+        Ok(parser.parse()?.get("n").unwrap())
+    }
+
+    #[test]
+    fn reads_every_number_form() {
+        for keep_integers in [false, true] {
+            assert_eq!(number("1e-3", keep_integers), Ok(JsonType::Number(0.001)));
+            assert_eq!(number("1E+2", keep_integers), Ok(JsonType::Number(100.)));
+            assert_eq!(number("-2.5e2", keep_integers), Ok(JsonType::Number(-250.)));
+            assert_eq!(number("0.0", keep_integers), Ok(JsonType::Number(0.)));
+        }
+        match number("-0", false) {
+            Ok(JsonType::Number(val)) => assert!(val == 0. && val.is_sign_negative()),
+            other => panic!("-0 read as {:?}", other),
+        }
+        assert_eq!(number("-0", true), Ok(JsonType::Integer(0)));
+        assert_eq!(number("12", false), Ok(JsonType::Number(12.)));
+    }
+
+    #[test]
+    fn rejects_numbers_outside_the_grammar() {
+        let bad = [
+            ("01", "a number without leading zeros", "'1'"),
+            ("-01", "a number without leading zeros", "'1'"),
+            ("1.", "a digit after '.'", "'}'"),
+            ("1.e3", "a digit after '.'", "'e'"),
+            ("1e", "a digit in the exponent", "'}'"),
+            ("1e+", "a digit in the exponent", "'}'"),
+            (".5", "a value", "'.'"),
+            ("+1", "a value", "'+'"),
+            ("-", "a digit", "'}'"),
+            ("-a", "a digit", "'a'"),
+        ];
+        for (text, expected, found) in bad {
+            let err = number(text, true).unwrap_err();
+            assert_eq!(
+                (err.expected.as_str(), err.found.as_str()),
+                (expected, found),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn integers_too_big_for_an_i64_become_floats() {
+        let max = "9223372036854775807";
+        let min = "-9223372036854775808";
+        assert_eq!(number(max, true), Ok(JsonType::Integer(i64::MAX)));
+        assert_eq!(number(min, true), Ok(JsonType::Integer(i64::MIN)));
+        assert_eq!(
+            number("9223372036854775808", true),
+            Ok(JsonType::Number(9223372036854775808.))
+        );
+        assert_eq!(
+            number("-9223372036854775809", true),
+            Ok(JsonType::Number(-9223372036854775809.))
+        );
+        assert_eq!(
+            number("1000000000000000000000", true),
+            Ok(JsonType::Number(1e21))
+        );
+        assert_eq!(number(max, false), Ok(JsonType::Number(i64::MAX as f64)));
+    }
+
+    #[test]
+    fn escaped_strings_round_trip() {
+        let mut texts: Vec<String> = (0..0x80u32)
+            .map(|c| char::from_u32(c).unwrap().to_string())
+            .collect();
+        texts.push("A line with \"quotes\",\n\ta tab and a \\ backslash".to_string());
+        texts.push("\u{ffff}\u{10000}\u{10ffff}😀 ツ é".to_string());
+        texts.push("".to_string());
+
+        for text in texts {
+            let mut obj = JsonObject::new();
+            obj.set_string(text.as_str(), text.as_str());
+            let back = parse(&obj.stringify()).unwrap();
+            assert_eq!(back, obj, "{:?} didn't round trip", text);
+        }
+    }
+}
@@ -0,0 +1,408 @@
+// Command line tools for project files, meant for build scripts and CI. It loads and saves through
+// the same Document code the editor uses and doesn't need raylib or a window.
+//
+// Exit codes: 0 when everything is fine, 1 when a check failed (problems found, a file that isn't
+// formatted, a file that can't be loaded) and 2 when the command itself is wrong.
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+
+use dialogue_maker::{validate, Document, Node, NodeTypes, Operation, Severity};
+
+const USAGE: &str = "Usage: dialogue_maker-cli <command> [options]
+
+Commands:
+    validate [--strict] <file>...         Check files for problems, --strict also fails on warnings
+    convert --to <format> <file> [-o <output>]
+                                          Write a file as json (the current format) or dot (graphviz),
+                                          to stdout if there's no output
+    stats <file>...                       Count nodes, connections, characters and words
+    fmt [--check] <file>...               Rewrite files the way the editor saves them, --check only
+                                          lists the ones that would change";
+
+enum CliError {
+    Usage(String),
+    Failed, // already reported, only the exit code is left
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.split_first() {
+        Some((command, rest)) => run(command, rest),
+        None => Err(CliError::Usage("no command given".to_string())),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::Failed) => ExitCode::from(1),
+        Err(CliError::Usage(message)) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(command: &str, args: &[String]) -> Result<(), CliError> {
+    match command {
+        "validate" => validate_cmd(args),
+        "convert" => convert_cmd(args),
+        "stats" => stats_cmd(args),
+        "fmt" => fmt_cmd(args),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(CliError::Usage(format!("unknown command '{}'", command))),
+    }
+}
+
+// Splits the arguments into files and the flags that were asked for, anything else is an error
+fn parse_args<'a>(
+    args: &'a [String],
+    flags: &[&str],
+) -> Result<(Vec<&'a str>, Vec<&'a str>), CliError> {
+    let mut files = vec![];
+    let mut found_flags = vec![];
+
+    for arg in args {
+        if flags.contains(&arg.as_str()) {
+            found_flags.push(arg.as_str());
+        } else if arg.starts_with('-') {
+            return Err(CliError::Usage(format!("unknown option '{}'", arg)));
+        } else {
+            files.push(arg.as_str());
+        }
+    }
+
+    if files.is_empty() {
+        return Err(CliError::Usage("no files given".to_string()));
+    }
+    Ok((files, found_flags))
+}
+
+fn load(path: &str) -> Option<Document> {
+    match Document::load_from_file(path) {
+        Ok(document) => Some(document),
+        Err(err) => {
+            eprintln!("{}: could not load, {}", path, err);
+            None
+        }
+    }
+}
+
+fn validate_cmd(args: &[String]) -> Result<(), CliError> {
+    let (files, flags) = parse_args(args, &["--strict"])?;
+    let strict = flags.contains(&"--strict");
+
+    let mut failed = false;
+    for path in files {
+        let document = match load(path) {
+            Some(document) => document,
+            None => {
+                failed = true;
+                continue;
+            }
+        };
+
+        let problems = validate(&document.nodes, &document.variables);
+        for problem in &problems {
+            println!("{}: {}", path, problem);
+        }
+
+        let errors = problems
+            .iter()
+            .filter(|p| p.severity == Severity::Error)
+            .count();
+        let warnings = problems.len() - errors;
+        println!("{}: {} errors, {} warnings", path, errors, warnings);
+
+        if errors > 0 || (strict && warnings > 0) {
+            failed = true;
+        }
+    }
+
+    if failed {
+        return Err(CliError::Failed);
+    }
+    Ok(())
+}
+
+fn convert_cmd(args: &[String]) -> Result<(), CliError> {
+    let mut format = None;
+    let mut output = None;
+    let mut file = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--to" => format = args.next(),
+            "-o" | "--output" => output = args.next(),
+            _ if arg.starts_with('-') => {
+                return Err(CliError::Usage(format!("unknown option '{}'", arg)));
+            }
+            _ if file.is_some() => {
+                return Err(CliError::Usage("convert takes only one file".to_string()));
+            }
+            _ => file = Some(arg),
+        }
+    }
+
+    let format = match format {
+        Some(format) => format,
+        None => return Err(CliError::Usage("convert needs --to <format>".to_string())),
+    };
+    let file = match file {
+        Some(file) => file,
+        None => return Err(CliError::Usage("no file given".to_string())),
+    };
+
+    // Checked before loading so a typo doesn't look like a broken file
+    if format != "json" && format != "dot" {
+        return Err(CliError::Usage(format!(
+            "unknown format '{}', use json or dot",
+            format
+        )));
+    }
+    let document = load(file).ok_or(CliError::Failed)?;
+    let text = match format.as_str() {
+        "json" => document.to_json().stringify(),
+        _ => to_dot(&document.nodes),
+    };
+
+    match output {
+        Some(output) => {
+            if let Err(err) = std::fs::write(output, text) {
+                eprintln!("{}: could not write, {}", output, err);
+                return Err(CliError::Failed);
+            }
+        }
+        None => println!("{}", text),
+    }
+    Ok(())
+}
+
+// Graphviz, to look at the whole conversation at once or put it in a design doc
+fn to_dot(nodes: &[Node]) -> String {
+    let mut to_return = "digraph dialogue {\n    node [shape=box];\n".to_string();
+
+    for n in nodes {
+        let label = match n.node_type {
+            NodeTypes::Dialogue => format!(
+                "{}: {}",
+                n.character.clone().unwrap_or_default(),
+                n.dialogue.clone().unwrap_or_default()
+            ),
+            NodeTypes::Branches => "branches".to_string(),
+            NodeTypes::Conditional => {
+                format!("if {}", n.condition.clone().unwrap_or_default())
+            }
+            NodeTypes::SetFlag => format!(
+                "set {} to {}",
+                n.flag_to_set.clone().unwrap_or_default(),
+                n.value_to_set.unwrap_or_default()
+            ),
+            NodeTypes::EmitEvent => {
+                format!("emit {}", n.event_to_emit.clone().unwrap_or_default())
+            }
+            NodeTypes::Start => format!("start {}", n.entry_name.clone().unwrap_or_default()),
+            NodeTypes::End => match n.exit_code {
+                Some(code) => format!("end {}", code),
+                None => "end".to_string(),
+            },
+            NodeTypes::SetVariable => {
+                let operator = match n.operation {
+                    Some(Operation::Add) => "+=",
+                    Some(Operation::Subtract) => "-=",
+                    _ => "=",
+                };
+                format!(
+                    "{} {} {}",
+                    n.variable.clone().unwrap_or_default(),
+                    operator,
+                    n.variable_value.clone().unwrap_or_default()
+                )
+            }
+            NodeTypes::Random => format!("random ({})", n.random_mode.unwrap().name()),
+            _ => n.node_type.name().to_string(),
+        };
+        to_return.push_str(&format!(
+            "    \"{}\" [label=\"{}\"];\n",
+            dot_escape(&n.id),
+            dot_escape(&label)
+        ));
+    }
+
+    for n in nodes {
+        for (i, link) in n.front_links.iter().enumerate() {
+            if link.is_empty() {
+                continue;
+            }
+            let label = match n.node_type {
+                NodeTypes::Branches => {
+                    let text = n
+                        .branches
+                        .as_ref()
+                        .and_then(|b| b.get(i))
+                        .cloned()
+                        .unwrap_or_default();
+                    match n.branch_conditions.as_ref().and_then(|c| c.get(i)) {
+                        Some(condition) if !condition.trim().is_empty() => {
+                            format!("{} (if {})", text, condition)
+                        }
+                        _ => text,
+                    }
+                }
+                NodeTypes::Conditional => ["true", "false", "not set"][i.min(2)].to_string(),
+                NodeTypes::Random => match n.weights.as_ref().and_then(|w| w.get(i)) {
+                    Some(weight) => format!("weight {}", weight),
+                    None => "".to_string(),
+                },
+                _ => "".to_string(),
+            };
+            to_return.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                dot_escape(&n.id),
+                dot_escape(link),
+                dot_escape(&label)
+            ));
+        }
+
+        // Dashed, the editor doesn't draw them either
+        match n.jump_target.as_deref() {
+            Some(target) if !target.is_empty() => to_return.push_str(&format!(
+                "    \"{}\" -> \"{}\" [style=dashed];\n",
+                dot_escape(&n.id),
+                dot_escape(target)
+            )),
+            _ => {}
+        }
+    }
+
+    to_return.push('}');
+    to_return
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn stats_cmd(args: &[String]) -> Result<(), CliError> {
+    let (files, _) = parse_args(args, &[])?;
+
+    let mut failed = false;
+    for path in files {
+        let document = match load(path) {
+            Some(document) => document,
+            None => {
+                failed = true;
+                continue;
+            }
+        };
+        let nodes = &document.nodes;
+
+        // BTreeMaps so the output is always in the same order
+        let mut types: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut characters: BTreeMap<String, (usize, usize)> = BTreeMap::new(); // lines, words
+        let mut words = 0;
+        for n in nodes {
+            *types.entry(n.node_type.name()).or_default() += 1;
+
+            if n.node_type == NodeTypes::Dialogue {
+                let line_words = n
+                    .dialogue
+                    .as_deref()
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .count();
+                words += line_words;
+
+                let character = characters
+                    .entry(n.character.clone().unwrap_or_default())
+                    .or_default();
+                character.0 += 1;
+                character.1 += line_words;
+            }
+        }
+        let connections = nodes
+            .iter()
+            .flat_map(|n| n.front_links.iter())
+            .filter(|l| !l.is_empty())
+            .count();
+
+        println!("{}:", path);
+        println!("    nodes: {}", nodes.len());
+        for (name, count) in &types {
+            println!("        {}: {}", name, count);
+        }
+        let mut starts: Vec<&str> = nodes
+            .iter()
+            .filter(|n| n.node_type == NodeTypes::Start)
+            .filter_map(|n| n.entry_name.as_deref())
+            .collect();
+        starts.sort();
+        if !starts.is_empty() {
+            println!("    starts: {}", starts.join(", "));
+        }
+        println!("    connections: {}", connections);
+        if !document.variables.is_empty() {
+            println!("    variables: {}", document.variables.len());
+        }
+        println!("    words of dialogue: {}", words);
+        println!("    characters: {}", characters.len());
+        for (name, (lines, words)) in &characters {
+            let name = if name.is_empty() { "(no name)" } else { name };
+            println!("        {}: {} lines, {} words", name, lines, words);
+        }
+    }
+
+    if failed {
+        return Err(CliError::Failed);
+    }
+    Ok(())
+}
+
+fn fmt_cmd(args: &[String]) -> Result<(), CliError> {
+    let (files, flags) = parse_args(args, &["--check"])?;
+    let check = flags.contains(&"--check");
+
+    let mut failed = false;
+    for path in files {
+        let current = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("{}: could not load, {}", path, err);
+                failed = true;
+                continue;
+            }
+        };
+        let document = match Document::load_from_str(&current) {
+            Ok(document) => document,
+            Err(err) => {
+                eprintln!("{}: could not load, {}", path, err);
+                failed = true;
+                continue;
+            }
+        };
+
+        let formatted = document.to_json().stringify();
+        if formatted == current {
+            continue;
+        }
+
+        if check {
+            println!("{}: not formatted", path);
+            failed = true;
+        } else if let Err(err) = std::fs::write(path, formatted) {
+            eprintln!("{}: could not write, {}", path, err);
+            failed = true;
+        } else {
+            println!("{}: formatted", path);
+        }
+    }
+
+    if failed {
+        return Err(CliError::Failed);
+    }
+    Ok(())
+}
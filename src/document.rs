@@ -0,0 +1,944 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use json_parser::{JsonError, JsonObject, JsonType, Parser};
+
+use crate::expression;
+use crate::migrations::{self, FORMAT_VERSION};
+use crate::node::{Node, NodeTypes, RandomMode};
+use crate::variables::{Operation, Value, VarType, Variable};
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(std::io::Error),
+    Json(JsonError),
+    InvalidNode { id: String, reason: String },
+    InvalidVariable { name: String, reason: String },
+    NewerVersion(i64), // the version the file was saved with
+    InvalidVersion,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "{}", err),
+            LoadError::Json(err) => write!(f, "{}", err),
+            LoadError::InvalidNode { id, reason } => write!(f, "node '{}': {}", id, reason),
+            LoadError::InvalidVariable { name, reason } => {
+                write!(f, "variable '{}': {}", name, reason)
+            }
+            LoadError::NewerVersion(version) => write!(
+                f,
+                "the file uses format version {}, but this version of dialogue_maker only \
+                 understands up to {}, update it to open the file",
+                version, FORMAT_VERSION
+            ),
+            LoadError::InvalidVersion => write!(f, "'version' must be a whole number from 1 up"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<std::io::Error> for LoadError {
+    fn from(err: std::io::Error) -> LoadError {
+        LoadError::Io(err)
+    }
+}
+
+impl From<JsonError> for LoadError {
+    fn from(err: JsonError) -> LoadError {
+        LoadError::Json(err)
+    }
+}
+
+// Everything else in the root of a file is a node, keyed by its id
+pub const NOT_NODE_KEYS: [&str; 3] = ["version", "variables", "editor"];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CardLayout {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraLayout {
+    pub x: f32,
+    pub y: f32,
+    pub zoom: f32,
+}
+
+// Where things are in the editor, games never need any of this
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EditorLayout {
+    pub camera: Option<CameraLayout>,
+    pub cards: HashMap<String, CardLayout>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Document {
+    pub nodes: Vec<Node>,
+    pub variables: Vec<Variable>, // in the order they were declared
+    pub layout: EditorLayout,
+    pub upgraded_from: Option<i64>, // format version of the file, if it was older than this one
+}
+
+impl Document {
+    pub fn new() -> Document {
+        Document::default()
+    }
+
+    pub fn load_from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Document, LoadError> {
+        let text = std::fs::read_to_string(path)?;
+        Document::load_from_str(&text)
+    }
+
+    pub fn load_from_str(text: &str) -> Result<Document, LoadError> {
+        let mut parser = Parser::new();
+        parser.load(text);
+        let parsed_obj = parser.parse().map_err(JsonError::from)?;
+
+        Document::from_json(parsed_obj)
+    }
+
+    pub fn from_json(parsed_obj: JsonObject) -> Result<Document, LoadError> {
+        let mut to_return = Document::new();
+
+        let version = migrations::get_version(&parsed_obj).ok_or(LoadError::InvalidVersion)?;
+        if version > FORMAT_VERSION {
+            return Err(LoadError::NewerVersion(version));
+        }
+        let parsed_obj = if version < FORMAT_VERSION {
+            to_return.upgraded_from = Some(version);
+            migrations::migrate(parsed_obj, version)
+        } else {
+            parsed_obj
+        };
+
+        let variables_obj = parsed_obj.get_obj("variables").ok();
+        let editor_obj = parsed_obj.get_obj("editor").ok();
+
+        for (n_id, n_obj) in parsed_obj.children {
+            if NOT_NODE_KEYS.contains(&n_id.as_str()) {
+                continue;
+            }
+            match n_obj {
+                JsonType::Object(obj) => to_return.nodes.push(node_from_json(n_id, &obj)?),
+                _ => return Err(invalid_node(&n_id, "node must be an object")),
+            }
+        }
+
+        if let Some(variables) = variables_obj {
+            for (name, var_obj) in variables.children {
+                match var_obj {
+                    JsonType::Object(obj) => {
+                        to_return.variables.push(variable_from_json(name, &obj)?)
+                    }
+                    _ => return Err(invalid_variable(&name, "variable must be an object")),
+                }
+            }
+        }
+
+        if let Some(editor) = editor_obj {
+            to_return.layout = layout_from_json(&editor);
+        }
+
+        Ok(to_return)
+    }
+
+    pub fn save_to_file<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json().stringify())
+    }
+
+    pub fn to_json(&self) -> JsonObject {
+        let mut obj = JsonObject::new();
+        obj.set_integer("version", FORMAT_VERSION);
+
+        // Filled in first and pushed once, looking each id up again after pushing it made
+        // saving quadratic. Ids and names are already unique so nothing needs replacing.
+        for n in &self.nodes {
+            // Only Node::default() has no type, it would never load again
+            if n.node_type == NodeTypes::SomethingHasGoneReallyWrong {
+                continue;
+            }
+            let mut node_obj = JsonObject::new();
+            node_to_json(n, &mut node_obj);
+            obj.children
+                .push((n.id.clone(), JsonType::Object(node_obj)));
+        }
+
+        let mut variables_obj = JsonObject::new();
+        for v in &self.variables {
+            let mut var_obj = JsonObject::new();
+            variable_to_json(v, &mut var_obj);
+            variables_obj
+                .children
+                .push((v.name.clone(), JsonType::Object(var_obj)));
+        }
+        obj.children
+            .push(("variables".to_string(), JsonType::Object(variables_obj)));
+
+        // Editor only data, game code only needs the nodes and "variables"
+        obj.push_obj("editor");
+        layout_to_json(&self.layout, obj.get_obj_ref("editor").unwrap());
+
+        obj
+    }
+
+    pub fn get_node(&self, id: &str) -> Option<&Node> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+
+    pub fn get_node_mut(&mut self, id: &str) -> Option<&mut Node> {
+        self.nodes.iter_mut().find(|n| n.id == id)
+    }
+
+    pub fn get_free_node_id(&self) -> String {
+        get_free_node_id(&self.nodes)
+    }
+
+    pub fn remove_node(&mut self, id: &str) -> Option<Node> {
+        self.layout.cards.remove(id);
+        remove_node(&mut self.nodes, id)
+    }
+
+    pub fn get_variable(&self, name: &str) -> Option<&Variable> {
+        self.variables.iter().find(|v| v.name == name)
+    }
+
+    // A new document with only the given nodes, their cards and the variables they set, no camera
+    pub fn subset(&self, ids: &HashSet<String>) -> Document {
+        let mut to_return = Document::new();
+        to_return.nodes = copy_nodes(&self.nodes, ids);
+        to_return.variables = used_variables(&self.variables, &to_return.nodes);
+        for (id, card) in &self.layout.cards {
+            if ids.contains(id) {
+                to_return.layout.cards.insert(id.clone(), *card);
+            }
+        }
+
+        to_return
+    }
+
+    // Adds all of other's nodes with new ids, gives back the new id of each old one
+    pub fn merge(&mut self, other: Document) -> HashMap<String, String> {
+        add_missing_variables(&mut self.variables, other.variables);
+        let new_ids = paste_nodes(&mut self.nodes, other.nodes);
+        for (old_id, card) in other.layout.cards {
+            if let Some(new_id) = new_ids.get(&old_id) {
+                self.layout.cards.insert(new_id.clone(), card);
+            }
+        }
+
+        new_ids
+    }
+}
+
+// Also disconnects every output that pointed at the removed node
+pub fn remove_node(nodes: &mut Vec<Node>, id: &str) -> Option<Node> {
+    let i = nodes.iter().position(|n| n.id == id)?;
+    let to_return = nodes.remove(i);
+
+    for n in nodes.iter_mut() {
+        for link in n.front_links.iter_mut() {
+            if link == id {
+                *link = "".to_string();
+            }
+        }
+        // Ids get reused, a jump left pointing at it could end up somewhere else later
+        if n.jump_target.as_deref() == Some(id) {
+            n.jump_target = Some("".to_string());
+        }
+    }
+
+    Some(to_return)
+}
+
+pub fn get_free_node_id(nodes: &[Node]) -> String {
+    'outer_loop: for i in 1..99999 {
+        let cur_i = format!("{:0>5}", i.to_string()); // i with left 0 tabs
+
+        for j in nodes {
+            if j.id == cur_i {
+                continue 'outer_loop;
+            }
+        }
+
+        return cur_i;
+    }
+
+    panic!("There isn't enough ids.");
+}
+
+// Outputs and jumps that go to nodes that weren't copied get disconnected
+pub fn copy_nodes(nodes: &[Node], ids: &HashSet<String>) -> Vec<Node> {
+    let mut to_return: Vec<Node> = nodes
+        .iter()
+        .filter(|n| ids.contains(&n.id))
+        .cloned()
+        .collect();
+
+    for n in to_return.iter_mut() {
+        for link in n.front_links.iter_mut() {
+            if !ids.contains(link) {
+                *link = "".to_string();
+            }
+        }
+        if let Some(target) = n.jump_target.as_mut() {
+            if !ids.contains(target) {
+                *target = "".to_string();
+            }
+        }
+    }
+
+    to_return
+}
+
+// Pasted nodes get free ids so they never clash with the ones already there, links and jumps
+// between them follow the new ids and links to anything else get disconnected
+pub fn paste_nodes(nodes: &mut Vec<Node>, pasted: Vec<Node>) -> HashMap<String, String> {
+    let mut new_ids = HashMap::new();
+    let first_pasted = nodes.len();
+
+    for mut n in pasted {
+        let new_id = get_free_node_id(nodes);
+        new_ids.insert(n.id.clone(), new_id.clone());
+        n.id = new_id;
+        nodes.push(n);
+    }
+
+    for n in nodes[first_pasted..].iter_mut() {
+        let links = n.front_links.iter_mut().chain(n.jump_target.as_mut());
+        for link in links {
+            *link = match new_ids.get(link) {
+                Some(new_id) => new_id.clone(),
+                None => "".to_string(),
+            };
+        }
+    }
+
+    new_ids
+}
+
+// The id of the node a jump typed as text goes to. Start names come first, so a jump can be
+// written as the name of the conversation it goes to, anything else has to be an id. When starts
+// share a name it's the last one, the same one the runtime starts at for that name.
+pub fn find_jump_target(nodes: &[Node], text: &str) -> Option<String> {
+    let text = text.trim();
+    let start = nodes.iter().rev().find(|n| {
+        n.node_type == NodeTypes::Start && n.entry_name.as_deref().map(|e| e.trim()) == Some(text)
+    });
+    match start {
+        Some(start) => Some(start.id.clone()),
+        None => nodes.iter().find(|n| n.id == text).map(|n| n.id.clone()),
+    }
+}
+
+// The declarations of the variables that the given nodes set or check in their conditions
+pub fn used_variables(variables: &[Variable], nodes: &[Node]) -> Vec<Variable> {
+    let mut used: HashSet<String> = HashSet::new();
+    for n in nodes {
+        if let Some(variable) = &n.variable {
+            used.insert(variable.trim().to_string());
+        }
+
+        let conditions = n
+            .condition
+            .iter()
+            .chain(n.branch_conditions.iter().flatten());
+        for source in conditions {
+            if let Ok(condition) = expression::parse(source) {
+                used.extend(condition.names().into_iter().map(|name| name.to_string()));
+            }
+        }
+    }
+
+    variables
+        .iter()
+        .filter(|v| used.contains(&v.name))
+        .cloned()
+        .collect()
+}
+
+// Variables that already exist keep their type and default
+pub fn add_missing_variables(variables: &mut Vec<Variable>, added: Vec<Variable>) {
+    for v in added {
+        if !variables.iter().any(|existing| existing.name == v.name) {
+            variables.push(v);
+        }
+    }
+}
+
+fn invalid_node<T: ToString>(id: &str, reason: T) -> LoadError {
+    LoadError::InvalidNode {
+        id: id.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn invalid_variable<T: ToString>(name: &str, reason: T) -> LoadError {
+    LoadError::InvalidVariable {
+        name: name.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn get_string(id: &str, obj: &JsonObject, key: &str) -> Result<String, LoadError> {
+    obj.get_string(key)
+        .map_err(|err| invalid_node(id, format!("'{}': {}", key, err)))
+}
+
+fn get_string_pairs(
+    id: &str,
+    obj: &JsonObject,
+    key: &str,
+) -> Result<Vec<(String, String)>, LoadError> {
+    let pairs_obj = obj
+        .get_obj(key)
+        .map_err(|err| invalid_node(id, format!("'{}': {}", key, err)))?;
+
+    let mut to_return = vec![];
+    for (pair_key, pair_val) in pairs_obj.children {
+        match pair_val {
+            JsonType::String(val) => to_return.push((pair_key, val)),
+            _ => {
+                return Err(invalid_node(
+                    id,
+                    format!("'{}' values must be strings", key),
+                ))
+            }
+        }
+    }
+
+    Ok(to_return)
+}
+
+// Text, next, condition and whether it's shown disabled, the last two are only saved when set
+fn get_branches(
+    id: &str,
+    obj: &JsonObject,
+) -> Result<Vec<(String, String, String, bool)>, LoadError> {
+    let items = obj
+        .get_array("branches")
+        .map_err(|err| invalid_node(id, format!("'branches': {}", err)))?;
+
+    let mut to_return = vec![];
+    for item in items {
+        match item {
+            JsonType::Object(branch) => to_return.push((
+                get_string(id, &branch, "text")?,
+                get_string(id, &branch, "next")?,
+                match branch.get("condition") {
+                    Ok(_) => get_string(id, &branch, "condition")?,
+                    Err(_) => "".to_string(),
+                },
+                match branch.get("show_disabled") {
+                    Ok(_) => branch
+                        .get_bool("show_disabled")
+                        .map_err(|err| invalid_node(id, format!("'show_disabled': {}", err)))?,
+                    Err(_) => false,
+                },
+            )),
+            _ => return Err(invalid_node(id, "'branches' items must be objects")),
+        }
+    }
+
+    Ok(to_return)
+}
+
+pub fn node_to_json(n: &Node, sub_obj: &mut JsonObject) {
+    sub_obj.set_string("type", n.node_type.name());
+    match n.node_type {
+        NodeTypes::Dialogue => {
+            sub_obj.set_string("character", &n.character.clone().unwrap());
+            sub_obj.set_string("dialogue", &n.dialogue.clone().unwrap());
+            sub_obj.set_string("next", &n.front_links[0]);
+        }
+        NodeTypes::Branches => {
+            // An array and not an object keyed by the text, so options with the same text and
+            // the order of the options both survive
+            let mut exits = vec![];
+            for (i, o) in n.branches.clone().unwrap().iter().enumerate() {
+                let mut exit = JsonObject::new();
+                exit.set_string("text", o);
+                exit.set_string("next", &n.front_links[i]);
+                let condition = &n.branch_conditions.as_ref().unwrap()[i];
+                if !condition.is_empty() {
+                    exit.set_string("condition", condition);
+                }
+                if n.branch_show_disabled.as_ref().unwrap()[i] {
+                    exit.set_bool("show_disabled", true);
+                }
+                exits.push(JsonType::Object(exit));
+            }
+            sub_obj.set_array("branches", exits);
+        }
+        NodeTypes::Conditional => {
+            sub_obj.set_string("condition", &n.condition.clone().unwrap());
+            sub_obj.push_obj("if");
+            let exits = sub_obj.get_obj_ref("if").unwrap();
+            exits.set_string("true", &n.front_links[0]);
+            exits.set_string("false", &n.front_links[1]);
+            exits.set_string("not_set", &n.front_links[2]);
+        }
+        NodeTypes::SetFlag => {
+            sub_obj.set_string("flag_to_set", &n.flag_to_set.clone().unwrap());
+            sub_obj.set_bool("value", n.value_to_set.unwrap());
+            sub_obj.set_string("next", &n.front_links[0]);
+        }
+        NodeTypes::EmitEvent => {
+            sub_obj.set_string("event", &n.event_to_emit.clone().unwrap());
+            sub_obj.push_obj("args");
+            let exits = sub_obj.get_obj_ref("args").unwrap();
+            for o in n.event_data.clone().unwrap() {
+                exits.set_string(&o.0, &o.1);
+            }
+            sub_obj.set_string("next", &n.front_links[0]);
+        }
+        NodeTypes::Start => {
+            sub_obj.set_string("name", &n.entry_name.clone().unwrap());
+            sub_obj.set_string("next", &n.front_links[0]);
+        }
+        NodeTypes::End => {
+            if let Some(code) = n.exit_code {
+                sub_obj.set_integer("exit_code", code);
+            }
+        }
+        NodeTypes::SetVariable => {
+            sub_obj.set_string("variable", &n.variable.clone().unwrap());
+            sub_obj.set_string("operation", n.operation.unwrap().name());
+            sub_obj.set_string("value", &n.variable_value.clone().unwrap());
+            sub_obj.set_string("next", &n.front_links[0]);
+        }
+        NodeTypes::Random => {
+            sub_obj.set_string("mode", n.random_mode.unwrap().name());
+            // An array like the branches, the order matters for the cycle mode
+            let mut outputs = vec![];
+            for (i, weight) in n.weights.clone().unwrap().iter().enumerate() {
+                let mut output = JsonObject::new();
+                output.set_integer("weight", *weight);
+                output.set_string("next", &n.front_links[i]);
+                outputs.push(JsonType::Object(output));
+            }
+            sub_obj.set_array("outputs", outputs);
+        }
+        NodeTypes::Jump => {
+            sub_obj.set_string("target", &n.jump_target.clone().unwrap());
+        }
+        NodeTypes::SomethingHasGoneReallyWrong => {} // nothing in it to save
+    }
+}
+
+pub fn node_from_json(n_id: String, obj: &JsonObject) -> Result<Node, LoadError> {
+    let n_type = get_string(&n_id, obj, "type")?;
+
+    match n_type.as_str() {
+        "dialogue" => Ok(Node::new_dialogue(
+            n_id.clone(),
+            get_string(&n_id, obj, "character")?,
+            get_string(&n_id, obj, "dialogue")?,
+            vec![get_string(&n_id, obj, "next")?],
+        )),
+        "branches" => {
+            let mut branches_vec: Vec<String> = vec![];
+            let mut front_vec: Vec<String> = vec![];
+            let mut conditions_vec: Vec<String> = vec![];
+            let mut show_disabled_vec: Vec<bool> = vec![];
+
+            for (branch, next, condition, show_disabled) in get_branches(&n_id, obj)? {
+                branches_vec.push(branch);
+                front_vec.push(next);
+                conditions_vec.push(condition);
+                show_disabled_vec.push(show_disabled);
+            }
+
+            let mut to_return = Node::new_branches(n_id, branches_vec, front_vec);
+            to_return.branch_conditions = Some(conditions_vec);
+            to_return.branch_show_disabled = Some(show_disabled_vec);
+            Ok(to_return)
+        }
+        "conditional" => {
+            let exits = obj
+                .get_obj("if")
+                .map_err(|err| invalid_node(&n_id, format!("'if': {}", err)))?;
+
+            Ok(Node::new_conditional(
+                n_id.clone(),
+                get_string(&n_id, obj, "condition")?,
+                vec![
+                    get_string(&n_id, &exits, "true")?,
+                    get_string(&n_id, &exits, "false")?,
+                    get_string(&n_id, &exits, "not_set")?,
+                ],
+            ))
+        }
+        "set_flag" => Ok(Node::new_set_flag(
+            n_id.clone(),
+            get_string(&n_id, obj, "flag_to_set")?,
+            obj.get_bool("value")
+                .map_err(|err| invalid_node(&n_id, format!("'value': {}", err)))?,
+            vec![get_string(&n_id, obj, "next")?],
+        )),
+        "emit_event" => Ok(Node::new_emit_event(
+            n_id.clone(),
+            get_string(&n_id, obj, "event")?,
+            get_string_pairs(&n_id, obj, "args")?,
+            vec![get_string(&n_id, obj, "next")?],
+        )),
+        "start" => Ok(Node::new_start(
+            n_id.clone(),
+            get_string(&n_id, obj, "name")?,
+            vec![get_string(&n_id, obj, "next")?],
+        )),
+        "end" => {
+            let exit_code = match obj.get("exit_code") {
+                Err(_) | Ok(JsonType::Null) => None,
+                Ok(_) => Some(
+                    obj.get_integer("exit_code")
+                        .map_err(|err| invalid_node(&n_id, format!("'exit_code': {}", err)))?,
+                ),
+            };
+            Ok(Node::new_end(n_id, exit_code))
+        }
+        "set_variable" => {
+            let operation = get_string(&n_id, obj, "operation")?;
+            let operation = match Operation::from_name(&operation) {
+                Some(found) => found,
+                None => {
+                    return Err(invalid_node(
+                        &n_id,
+                        format!("unknown operation '{}'", operation),
+                    ))
+                }
+            };
+
+            Ok(Node::new_set_variable(
+                n_id.clone(),
+                get_string(&n_id, obj, "variable")?,
+                operation,
+                get_string(&n_id, obj, "value")?,
+                vec![get_string(&n_id, obj, "next")?],
+            ))
+        }
+        "random" => {
+            let mode = get_string(&n_id, obj, "mode")?;
+            let mode = match RandomMode::from_name(&mode) {
+                Some(found) => found,
+                None => return Err(invalid_node(&n_id, format!("unknown mode '{}'", mode))),
+            };
+
+            let items = obj
+                .get_array("outputs")
+                .map_err(|err| invalid_node(&n_id, format!("'outputs': {}", err)))?;
+            let mut weights = vec![];
+            let mut front_vec = vec![];
+            for item in items {
+                let output = match item {
+                    JsonType::Object(output) => output,
+                    _ => return Err(invalid_node(&n_id, "'outputs' items must be objects")),
+                };
+                weights.push(
+                    output
+                        .get_integer("weight")
+                        .map_err(|err| invalid_node(&n_id, format!("'weight': {}", err)))?,
+                );
+                front_vec.push(get_string(&n_id, &output, "next")?);
+            }
+
+            Ok(Node::new_random(n_id, weights, mode, front_vec))
+        }
+        "jump" => Ok(Node::new_jump(
+            n_id.clone(),
+            get_string(&n_id, obj, "target")?,
+        )),
+        _ => Err(invalid_node(
+            &n_id,
+            format!("unknown node type '{}'", n_type),
+        )),
+    }
+}
+
+fn variable_to_json(v: &Variable, var_obj: &mut JsonObject) {
+    var_obj.set_string("type", v.default.var_type().name());
+    match &v.default {
+        Value::Int(val) => var_obj.set_integer("default", *val),
+        Value::Float(val) => var_obj.set_number("default", *val),
+        Value::String(val) => var_obj.set_string("default", val),
+    }
+}
+
+fn variable_from_json(name: String, obj: &JsonObject) -> Result<Variable, LoadError> {
+    let type_name = obj
+        .get_string("type")
+        .map_err(|err| invalid_variable(&name, format!("'type': {}", err)))?;
+    let var_type = match VarType::from_name(&type_name) {
+        Some(found) => found,
+        None => {
+            return Err(invalid_variable(
+                &name,
+                format!("unknown type '{}'", type_name),
+            ))
+        }
+    };
+
+    let default = match var_type {
+        VarType::Int => obj.get_integer("default").map(Value::Int),
+        VarType::Float => obj.get_number("default").map(Value::Float),
+        VarType::String => obj.get_string("default").map(Value::String),
+    }
+    .map_err(|err| invalid_variable(&name, format!("'default': {}", err)))?;
+
+    Ok(Variable { name, default })
+}
+
+fn layout_to_json(layout: &EditorLayout, editor_obj: &mut JsonObject) {
+    if let Some(cam) = &layout.camera {
+        editor_obj.push_obj("camera");
+        let cam_obj = editor_obj.get_obj_ref("camera").unwrap();
+        cam_obj.set_number("x", cam.x as f64);
+        cam_obj.set_number("y", cam.y as f64);
+        cam_obj.set_number("zoom", cam.zoom as f64);
+    }
+
+    // Sorted so saving the same project twice gives the same file
+    let mut ids: Vec<&String> = layout.cards.keys().collect();
+    ids.sort();
+
+    let mut cards_obj = JsonObject::new();
+    for id in ids {
+        let card = &layout.cards[id];
+        let mut card_obj = JsonObject::new();
+        card_obj.set_number("x", card.x as f64);
+        card_obj.set_number("y", card.y as f64);
+        card_obj.set_number("width", card.width as f64);
+        card_obj.set_number("height", card.height as f64);
+        cards_obj
+            .children
+            .push((id.clone(), JsonType::Object(card_obj)));
+    }
+    editor_obj
+        .children
+        .push(("cards".to_string(), JsonType::Object(cards_obj)));
+}
+
+// Missing or broken entries are skipped, the editor just lays those cards out by itself
+fn layout_from_json(editor_obj: &JsonObject) -> EditorLayout {
+    let mut to_return = EditorLayout::default();
+
+    if let Ok(cam_obj) = editor_obj.get_obj("camera") {
+        if let (Ok(x), Ok(y), Ok(zoom)) = (
+            cam_obj.get_number("x"),
+            cam_obj.get_number("y"),
+            cam_obj.get_number("zoom"),
+        ) {
+            if zoom > 0. {
+                to_return.camera = Some(CameraLayout {
+                    x: x as f32,
+                    y: y as f32,
+                    zoom: zoom as f32,
+                });
+            }
+        }
+    }
+
+    let cards_obj = match editor_obj.get_obj("cards") {
+        Ok(found) => found,
+        Err(_) => return to_return,
+    };
+
+    for (id, card_obj) in cards_obj.children {
+        let card_obj = match card_obj {
+            JsonType::Object(found) => found,
+            _ => continue,
+        };
+
+        if let (Ok(x), Ok(y), Ok(width), Ok(height)) = (
+            card_obj.get_number("x"),
+            card_obj.get_number("y"),
+            card_obj.get_number("width"),
+            card_obj.get_number("height"),
+        ) {
+            to_return.cards.insert(
+                id,
+                CardLayout {
+                    x: x as f32,
+                    y: y as f32,
+                    width: width as f32,
+                    height: height as f32,
+                },
+            );
+        }
+    }
+
+    to_return
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn every_node_type() -> Vec<Node> {
+        let mut branches = Node::new_branches(
+            "00002",
+            links(&["Open", "Open", "Leave"]),
+            links(&["00003", "", "00010"]),
+        );
+        branches.branch_conditions = Some(links(&["", "gold > 1", "flag(\"met\")"]));
+        branches.branch_show_disabled = Some(vec![false, true, false]);
+
+        vec![
+            Node::new_start("00001", "intro", links(&["00011"])),
+            branches,
+            Node::new_conditional(
+                "00003",
+                "has_key and gold >= 2",
+                links(&["00004", "", "00005"]),
+            ),
+            Node::new_set_flag("00004", "met \"Bob\"", true, links(&["00005"])),
+            Node::new_emit_event(
+                "00005",
+                "play_sound",
+                vec![("name".to_string(), "door".to_string())],
+                links(&["00006"]),
+            ),
+            Node::new_set_variable("00006", "gold", Operation::Subtract, "2", links(&["00007"])),
+            Node::new_random(
+                "00007",
+                vec![3, 0, 1],
+                RandomMode::Shuffle,
+                links(&["00008", "", "00009"]),
+            ),
+            Node::new_end("00008", Some(-2)),
+            Node::new_end("00009", None),
+            Node::new_jump("00010", "00001"),
+            Node::new_dialogue("00011", "Bob", "Hi,\n\"you\" ✓", links(&["00002"])),
+        ]
+    }
+
+    fn ids(nodes: &[Node]) -> Vec<&str> {
+        nodes.iter().map(|n| n.id.as_str()).collect()
+    }
+
+    #[test]
+    fn every_node_type_survives_saving_and_loading() {
+        let mut document = Document {
+            nodes: every_node_type(),
+            variables: vec![
+                Variable {
+                    name: "gold".to_string(),
+                    default: Value::Int(-3),
+                },
+                Variable {
+                    name: "speed".to_string(),
+                    default: Value::Float(1.5),
+                },
+                Variable {
+                    name: "title".to_string(),
+                    default: Value::String("Sir".to_string()),
+                },
+            ],
+            ..Document::default()
+        };
+        document.layout.camera = Some(CameraLayout {
+            x: -10.,
+            y: 20.5,
+            zoom: 2.,
+        });
+        document.layout.cards.insert(
+            "00001".to_string(),
+            CardLayout {
+                x: 1.,
+                y: 2.,
+                width: 300.,
+                height: 120.5,
+            },
+        );
+
+        let text = document.to_json().stringify();
+        let loaded = Document::load_from_str(&text).unwrap();
+        assert_eq!(loaded, document);
+        assert_eq!(loaded.to_json().stringify(), text);
+    }
+
+    #[test]
+    fn nodes_without_a_type_are_left_out() {
+        let document = Document {
+            nodes: vec![Node::default(), Node::new_end("00002", None)],
+            ..Document::default()
+        };
+        let loaded = Document::load_from_str(&document.to_json().stringify()).unwrap();
+        assert_eq!(loaded.nodes, vec![Node::new_end("00002", None)]);
+    }
+
+    #[test]
+    fn removing_a_node_disconnects_everything_that_went_to_it() {
+        let mut nodes = every_node_type();
+        let removed = remove_node(&mut nodes, "00001").unwrap();
+        assert_eq!(removed.id, "00001");
+        assert!(remove_node(&mut nodes, "00001").is_none());
+
+        let jump = nodes.iter().find(|n| n.id == "00010").unwrap();
+        assert_eq!(jump.jump_target.as_deref(), Some(""));
+
+        let mut nodes = every_node_type();
+        remove_node(&mut nodes, "00005");
+        assert_eq!(nodes[2].front_links, links(&["00004", "", ""]));
+        assert_eq!(nodes[3].front_links, links(&[""]));
+        assert!(nodes
+            .iter()
+            .all(|n| !n.front_links.contains(&"00005".to_string())));
+    }
+
+    #[test]
+    fn pasted_nodes_get_new_ids_and_keep_links_between_them() {
+        let mut nodes = vec![Node::new_end("00001", None), Node::new_end("00003", None)];
+        let pasted = vec![
+            Node::new_start("00001", "intro", links(&["00002"])),
+            Node::new_dialogue("00002", "Bob", "Hi", links(&["00005"])),
+            Node::new_jump("00003", "00001"),
+            Node::new_jump("00004", "00009"),
+        ];
+        let new_ids = paste_nodes(&mut nodes, pasted);
+
+        assert_eq!(
+            ids(&nodes),
+            ["00001", "00003", "00002", "00004", "00005", "00006"]
+        );
+        assert_eq!(new_ids["00001"], "00002");
+        assert_eq!(new_ids["00002"], "00004");
+        assert_eq!(new_ids["00003"], "00005");
+        assert_eq!(new_ids["00004"], "00006");
+        // Only links between pasted nodes are kept, the rest would go to the wrong node
+        assert_eq!(nodes[2].front_links, links(&["00004"]));
+        assert_eq!(nodes[3].front_links, links(&[""]));
+        assert_eq!(nodes[4].jump_target.as_deref(), Some("00002"));
+        assert_eq!(nodes[5].jump_target.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn jumps_find_starts_by_name_then_ids() {
+        let nodes = vec![
+            Node::new_start("00001", "intro", links(&[""])),
+            Node::new_start("00002", "00003", links(&[""])),
+            Node::new_start("00003", " intro ", links(&[""])),
+            Node::new_end("00004", None),
+        ];
+        // The last start with the name, like the runtime
+        assert_eq!(find_jump_target(&nodes, "intro"), Some("00003".to_string()));
+        assert_eq!(
+            find_jump_target(&nodes, " intro"),
+            Some("00003".to_string())
+        );
+        // Names come before ids
+        assert_eq!(find_jump_target(&nodes, "00003"), Some("00002".to_string()));
+        assert_eq!(find_jump_target(&nodes, "00004"), Some("00004".to_string()));
+        assert_eq!(find_jump_target(&nodes, "outro"), None);
+        assert_eq!(find_jump_target(&nodes, ""), None);
+    }
+}
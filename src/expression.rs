@@ -0,0 +1,806 @@
+// Conditions for the conditional nodes, like `gold >= 10 and not has_key`. They're saved as they
+// were typed and parsed again wherever they're needed, so the editor, the validator and the runtime
+// all read them the same way.
+//
+// Names are the declared variables, anything else is a flag. Flags that were never set are neither
+// true nor false, so a condition that depends on one takes the "not set" exit.
+use crate::variables::{Value, VarType, Variable};
+
+const KEYWORDS: [&str; 6] = ["and", "or", "not", "true", "false", "flag"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    Bool(bool),
+    Name(String),
+    Flag(String), // flag("..."), for flags whose names aren't valid names
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, Comparison, Box<Expr>),
+}
+
+// Where the values come from when a condition is checked
+pub trait Scope {
+    fn variable(&self, name: &str) -> Option<Value>;
+    fn flag(&self, name: &str) -> Option<bool>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ExprType {
+    Bool,
+    Value(VarType),
+}
+
+impl ExprType {
+    fn name(&self) -> &'static str {
+        match self {
+            ExprType::Bool => "true or false",
+            ExprType::Value(var_type) => var_type.name(),
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, ExprType::Value(VarType::Int | VarType::Float))
+    }
+}
+
+// What's left while evaluating, bools don't have a variable type
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+    Bool(bool),
+    Value(Value),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(Value),
+    Text(String),
+    Word(String),
+    Compare(Comparison),
+    Open,
+    Close,
+}
+
+// Parses and type checks, what the editor and the validator need
+pub fn check_condition(source: &str, variables: &[Variable]) -> Result<Expr, String> {
+    let expr = parse(source)?;
+    expr.check(variables)?;
+    Ok(expr)
+}
+
+pub fn parse(source: &str) -> Result<Expr, String> {
+    let tokens = tokenize(source)?;
+    if tokens.is_empty() {
+        return Err("the condition is empty".to_string());
+    }
+
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end_column: source.trim_end().chars().count() + 1,
+    };
+    let expr = parser.or()?;
+    match parser.tokens.get(parser.pos) {
+        Some((token, column)) => Err(format!(
+            "unexpected {} at column {}",
+            describe(token),
+            column
+        )),
+        None => Ok(expr),
+    }
+}
+
+// How to write a flag in a condition, the flag("...") form when it isn't a valid name or there's
+// a variable with the same name
+pub fn flag_reference(flag: &str, variable_names: &[String]) -> String {
+    if is_name(flag) && !variable_names.iter().any(|v| v == flag) {
+        return flag.to_string();
+    }
+    format!(
+        "flag(\"{}\")",
+        flag.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+// The condition with every use of the name written as the new one instead, for renaming a
+// variable. Words inside quotes and flag("...") are left alone, and so is a condition that
+// can't even be split into tokens, there's no telling what it meant.
+pub fn rename(source: &str, old_name: &str, new_name: &str) -> String {
+    let tokens = match tokenize(source) {
+        Ok(tokens) => tokens,
+        Err(_) => return source.to_string(),
+    };
+
+    let mut chars: Vec<char> = source.chars().collect();
+    // From the end so the columns of the ones before stay right
+    for (token, column) in tokens.iter().rev() {
+        if let Token::Word(word) = token {
+            if word == old_name {
+                let start = column - 1;
+                let end = start + word.chars().count();
+                chars.splice(start..end, new_name.chars());
+            }
+        }
+    }
+    chars.into_iter().collect()
+}
+
+// Whether a condition can read it as a name, variables that aren't could never be checked
+pub fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_') && !KEYWORDS.contains(&text)
+}
+
+impl Expr {
+    // Every name in the condition, variables and flags alike
+    pub fn names(&self) -> Vec<&str> {
+        match self {
+            Expr::Name(name) => vec![name],
+            Expr::Not(inner) => inner.names(),
+            Expr::And(a, b) | Expr::Or(a, b) | Expr::Compare(a, _, b) => {
+                let mut to_return = a.names();
+                to_return.extend(b.names());
+                to_return
+            }
+            _ => vec![],
+        }
+    }
+
+    // Every flag the condition reads
+    pub fn flags<'a>(&'a self, variables: &[Variable]) -> Vec<&'a str> {
+        let mut to_return = vec![];
+        self.collect_flags(variables, &mut to_return);
+        to_return
+    }
+
+    fn collect_flags<'a>(&'a self, variables: &[Variable], flags: &mut Vec<&'a str>) {
+        match self {
+            Expr::Name(name) if !variables.iter().any(|v| &v.name == name) => flags.push(name),
+            Expr::Flag(name) => flags.push(name),
+            Expr::Not(inner) => inner.collect_flags(variables, flags),
+            Expr::And(a, b) | Expr::Or(a, b) | Expr::Compare(a, _, b) => {
+                a.collect_flags(variables, flags);
+                b.collect_flags(variables, flags);
+            }
+            _ => {}
+        }
+    }
+
+    // The whole condition has to end up as true or false
+    pub fn check(&self, variables: &[Variable]) -> Result<(), String> {
+        match self.type_of(variables)? {
+            ExprType::Bool => Ok(()),
+            other => Err(format!(
+                "the condition has to be true or false, but it's a {}",
+                other.name()
+            )),
+        }
+    }
+
+    fn type_of(&self, variables: &[Variable]) -> Result<ExprType, String> {
+        match self {
+            Expr::Literal(value) => Ok(ExprType::Value(value.var_type())),
+            Expr::Bool(_) | Expr::Flag(_) => Ok(ExprType::Bool),
+            Expr::Name(name) => match variables.iter().find(|v| &v.name == name) {
+                Some(variable) => Ok(ExprType::Value(variable.default.var_type())),
+                None => Ok(ExprType::Bool),
+            },
+            Expr::Not(inner) => {
+                expect_bool("not", inner.type_of(variables)?)?;
+                Ok(ExprType::Bool)
+            }
+            Expr::And(a, b) | Expr::Or(a, b) => {
+                let word = if let Expr::And(_, _) = self {
+                    "and"
+                } else {
+                    "or"
+                };
+                expect_bool(word, a.type_of(variables)?)?;
+                expect_bool(word, b.type_of(variables)?)?;
+                Ok(ExprType::Bool)
+            }
+            Expr::Compare(a, comparison, b) => {
+                let a = a.type_of(variables)?;
+                let b = b.type_of(variables)?;
+                let ordered = !matches!(comparison, Comparison::Equal | Comparison::NotEqual);
+                if (a.is_number() && b.is_number()) || (a == b && !(ordered && a == ExprType::Bool))
+                {
+                    return Ok(ExprType::Bool);
+                }
+                if a == b {
+                    return Err("true or false can only be compared with == and !=".to_string());
+                }
+                Err(format!("can't compare a {} with a {}", a.name(), b.name()))
+            }
+        }
+    }
+
+    // None when it depends on a flag that isn't set. Conditions that passed check only fail here
+    // if the game gave a variable a value of another type.
+    pub fn eval(&self, scope: &impl Scope) -> Result<Option<bool>, String> {
+        match self.eval_operand(scope)? {
+            Some(Operand::Bool(result)) => Ok(Some(result)),
+            Some(Operand::Value(value)) => Err(format!(
+                "the condition has to be true or false, but it's a {}",
+                value.var_type().name()
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn eval_operand(&self, scope: &impl Scope) -> Result<Option<Operand>, String> {
+        let to_return = match self {
+            Expr::Literal(value) => Operand::Value(value.clone()),
+            Expr::Bool(value) => Operand::Bool(*value),
+            Expr::Name(name) => match scope.variable(name) {
+                Some(value) => Operand::Value(value),
+                None => match scope.flag(name) {
+                    Some(value) => Operand::Bool(value),
+                    None => return Ok(None),
+                },
+            },
+            Expr::Flag(name) => match scope.flag(name) {
+                Some(value) => Operand::Bool(value),
+                None => return Ok(None),
+            },
+            Expr::Not(inner) => match inner.eval(scope)? {
+                Some(value) => Operand::Bool(!value),
+                None => return Ok(None),
+            },
+            // An unset flag only matters if the other side doesn't already decide it
+            Expr::And(a, b) => match (a.eval(scope)?, b.eval(scope)?) {
+                (Some(false), _) | (_, Some(false)) => Operand::Bool(false),
+                (Some(true), Some(true)) => Operand::Bool(true),
+                _ => return Ok(None),
+            },
+            Expr::Or(a, b) => match (a.eval(scope)?, b.eval(scope)?) {
+                (Some(true), _) | (_, Some(true)) => Operand::Bool(true),
+                (Some(false), Some(false)) => Operand::Bool(false),
+                _ => return Ok(None),
+            },
+            Expr::Compare(a, comparison, b) => {
+                match (a.eval_operand(scope)?, b.eval_operand(scope)?) {
+                    (Some(a), Some(b)) => Operand::Bool(compare(&a, *comparison, &b)?),
+                    _ => return Ok(None),
+                }
+            }
+        };
+        Ok(Some(to_return))
+    }
+}
+
+fn expect_bool(word: &str, found: ExprType) -> Result<(), String> {
+    match found {
+        ExprType::Bool => Ok(()),
+        other => Err(format!(
+            "'{}' needs true or false, but got a {}",
+            word,
+            other.name()
+        )),
+    }
+}
+
+fn compare(a: &Operand, comparison: Comparison, b: &Operand) -> Result<bool, String> {
+    use std::cmp::Ordering;
+
+    let ordering = match (a, b) {
+        (Operand::Bool(a), Operand::Bool(b)) => a.cmp(b),
+        (Operand::Value(Value::String(a)), Operand::Value(Value::String(b))) => a.cmp(b),
+        (Operand::Value(Value::Int(a)), Operand::Value(Value::Int(b))) => a.cmp(b),
+        (Operand::Value(a), Operand::Value(b)) => match (as_number(a), as_number(b)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            _ => return Err("can't compare a string with a number".to_string()),
+        },
+        _ => return Err("can't compare true or false with a value".to_string()),
+    };
+
+    Ok(match comparison {
+        Comparison::Equal => ordering == Ordering::Equal,
+        Comparison::NotEqual => ordering != Ordering::Equal,
+        Comparison::Less => ordering == Ordering::Less,
+        Comparison::LessOrEqual => ordering != Ordering::Greater,
+        Comparison::Greater => ordering == Ordering::Greater,
+        Comparison::GreaterOrEqual => ordering != Ordering::Less,
+    })
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(val) => Some(*val as f64),
+        Value::Float(val) => Some(*val),
+        Value::String(_) => None,
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(value) => format!("number {}", value),
+        Token::Text(text) => format!("text \"{}\"", text),
+        Token::Word(word) => format!("'{}'", word),
+        Token::Compare(_) => "comparison".to_string(),
+        Token::Open => "'('".to_string(),
+        Token::Close => "')'".to_string(),
+    }
+}
+
+// Each token with the column it starts at, counting from 1
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut to_return = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+        let comparison = match two.as_str() {
+            "==" => Some((Comparison::Equal, 2)),
+            "!=" => Some((Comparison::NotEqual, 2)),
+            "<=" => Some((Comparison::LessOrEqual, 2)),
+            ">=" => Some((Comparison::GreaterOrEqual, 2)),
+            _ => match c {
+                '<' => Some((Comparison::Less, 1)),
+                '>' => Some((Comparison::Greater, 1)),
+                _ => None,
+            },
+        };
+        if let Some((comparison, len)) = comparison {
+            to_return.push((Token::Compare(comparison), column));
+            i += len;
+            continue;
+        }
+
+        let starts_number = c.is_ascii_digit()
+            || (c == '-' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit()));
+        if starts_number {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = if text.contains('.') {
+                Value::parse(&text, VarType::Float)
+            } else {
+                Value::parse(&text, VarType::Int)
+            };
+            match value {
+                Ok(value) => to_return.push((Token::Number(value), column)),
+                Err(_) => return Err(format!("'{}' at column {} is not a number", text, column)),
+            }
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            to_return.push((Token::Word(chars[start..i].iter().collect()), column));
+            continue;
+        }
+
+        match c {
+            '(' => to_return.push((Token::Open, column)),
+            ')' => to_return.push((Token::Close, column)),
+            '"' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        Some('"') => break,
+                        Some('\\') if i + 1 < chars.len() => {
+                            text.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(c) => {
+                            text.push(*c);
+                            i += 1;
+                        }
+                        None => {
+                            return Err(format!("the text at column {} is never closed", column))
+                        }
+                    }
+                }
+                to_return.push((Token::Text(text), column));
+            }
+            '=' => return Err(format!("use == to compare, at column {}", column)),
+            _ => return Err(format!("unexpected '{}' at column {}", c, column)),
+        }
+        i += 1;
+    }
+
+    Ok(to_return)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end_column: usize, // right after the last token
+}
+
+impl Parser {
+    fn peek_word(&self, word: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some((Token::Word(found), _)) if found == word)
+    }
+
+    // Where an error about the current token points to
+    fn column(&self) -> usize {
+        match self.tokens.get(self.pos) {
+            Some((_, column)) => *column,
+            None => self.end_column,
+        }
+    }
+
+    fn unexpected(&self) -> String {
+        match self.tokens.get(self.pos) {
+            Some((token, column)) => format!("unexpected {} at column {}", describe(token), column),
+            None => "the condition ends too soon".to_string(),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut to_return = self.and()?;
+        while self.peek_word("or") {
+            self.pos += 1;
+            to_return = Expr::Or(Box::new(to_return), Box::new(self.and()?));
+        }
+        Ok(to_return)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut to_return = self.not()?;
+        while self.peek_word("and") {
+            self.pos += 1;
+            to_return = Expr::And(Box::new(to_return), Box::new(self.not()?));
+        }
+        Ok(to_return)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.peek_word("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.primary()?;
+        let comparison = match self.tokens.get(self.pos) {
+            Some((Token::Compare(comparison), _)) => *comparison,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.primary()?;
+
+        if let Some((Token::Compare(_), column)) = self.tokens.get(self.pos) {
+            return Err(format!(
+                "comparisons can't be chained, use 'and' between them (column {})",
+                column
+            ));
+        }
+        Ok(Expr::Compare(Box::new(left), comparison, Box::new(right)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = match self.tokens.get(self.pos) {
+            Some((token, _)) => token.clone(),
+            None => return Err(self.unexpected()),
+        };
+
+        let to_return = match token {
+            Token::Number(value) => Expr::Literal(value),
+            Token::Text(text) => Expr::Literal(Value::String(text)),
+            Token::Open => {
+                self.pos += 1;
+                let inner = self.or()?;
+                match self.tokens.get(self.pos) {
+                    Some((Token::Close, _)) => {}
+                    _ => {
+                        return Err(format!(
+                            "missing ')' at column {}, for the '(' before it",
+                            self.column()
+                        ))
+                    }
+                }
+                inner
+            }
+            Token::Word(word) => match word.as_str() {
+                "true" => Expr::Bool(true),
+                "false" => Expr::Bool(false),
+                "flag" => {
+                    self.pos += 1;
+                    let name = match (self.tokens.get(self.pos), self.tokens.get(self.pos + 1)) {
+                        (Some((Token::Open, _)), Some((Token::Text(name), _))) => name.clone(),
+                        _ => {
+                            return Err(format!(
+                                "flag has to be followed by a name in quotes, like flag(\"name\"), at column {}",
+                                self.column()
+                            ))
+                        }
+                    };
+                    self.pos += 2;
+                    match self.tokens.get(self.pos) {
+                        Some((Token::Close, _)) => {}
+                        _ => return Err(format!("missing ')' at column {}", self.column())),
+                    }
+                    Expr::Flag(name)
+                }
+                "and" | "or" | "not" => return Err(self.unexpected()),
+                _ => Expr::Name(word),
+            },
+            Token::Compare(_) | Token::Close => return Err(self.unexpected()),
+        };
+
+        self.pos += 1;
+        Ok(to_return)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct TestScope {
+        variables: Vec<Variable>,
+        flags: HashMap<String, bool>,
+    }
+
+    impl Scope for TestScope {
+        fn variable(&self, name: &str) -> Option<Value> {
+            self.variables
+                .iter()
+                .find(|v| v.name == name)
+                .map(|v| v.default.clone())
+        }
+
+        fn flag(&self, name: &str) -> Option<bool> {
+            self.flags.get(name).copied()
+        }
+    }
+
+    fn variables() -> Vec<Variable> {
+        vec![
+            Variable {
+                name: "gold".to_string(),
+                default: Value::Int(5),
+            },
+            Variable {
+                name: "price".to_string(),
+                default: Value::Float(5.5),
+            },
+            Variable {
+                name: "name".to_string(),
+                default: Value::String("bob".to_string()),
+            },
+        ]
+    }
+
+    // Only the flags given are set
+    fn eval(source: &str, flags: &[(&str, bool)]) -> Option<bool> {
+        let scope = TestScope {
+            variables: variables(),
+            flags: flags.iter().map(|(f, v)| (f.to_string(), *v)).collect(),
+        };
+        check_condition(source, &scope.variables)
+            .unwrap()
+            .eval(&scope)
+            .unwrap()
+    }
+
+    fn name(name: &str) -> Box<Expr> {
+        Box::new(Expr::Name(name.to_string()))
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and_tighter_than_or() {
+        assert_eq!(
+            parse("a or b and not c").unwrap(),
+            Expr::Or(
+                name("a"),
+                Box::new(Expr::And(name("b"), Box::new(Expr::Not(name("c")))))
+            )
+        );
+        assert_eq!(
+            parse("(a or b) and c").unwrap(),
+            Expr::And(Box::new(Expr::Or(name("a"), name("b"))), name("c"))
+        );
+        assert_eq!(
+            parse("not gold >= 1").unwrap(),
+            Expr::Not(Box::new(Expr::Compare(
+                name("gold"),
+                Comparison::GreaterOrEqual,
+                Box::new(Expr::Literal(Value::Int(1)))
+            )))
+        );
+        assert_eq!(
+            parse("flag(\"my flag\") or not not x").unwrap(),
+            Expr::Or(
+                Box::new(Expr::Flag("my flag".to_string())),
+                Box::new(Expr::Not(Box::new(Expr::Not(name("x")))))
+            )
+        );
+    }
+
+    #[test]
+    fn and_or_not() {
+        let flags = [("yes", true), ("no", false)];
+        assert_eq!(eval("yes and no", &flags), Some(false));
+        assert_eq!(eval("yes and yes", &flags), Some(true));
+        assert_eq!(eval("no or yes", &flags), Some(true));
+        assert_eq!(eval("no or no", &flags), Some(false));
+        assert_eq!(eval("not no", &flags), Some(true));
+        assert_eq!(eval("no or yes and no", &flags), Some(false));
+        assert_eq!(eval("(no or yes) and not no", &flags), Some(true));
+        assert_eq!(eval("true and not false", &flags), Some(true));
+    }
+
+    #[test]
+    fn compares_numbers_of_either_type_and_text() {
+        assert_eq!(eval("gold < price", &[]), Some(true));
+        assert_eq!(eval("gold == 5.0", &[]), Some(true));
+        assert_eq!(eval("price >= 5.5 and price <= 5.5", &[]), Some(true));
+        assert_eq!(eval("gold != -5", &[]), Some(true));
+        assert_eq!(eval("gold > 5", &[]), Some(false));
+        assert_eq!(eval("name == \"bob\"", &[]), Some(true));
+        assert_eq!(eval("name < \"carl\"", &[]), Some(true));
+        assert_eq!(eval("yes == true", &[("yes", true)]), Some(true));
+        assert_eq!(
+            eval("yes != no", &[("yes", true), ("no", true)]),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn type_errors() {
+        let check = |source: &str| check_condition(source, &variables()).unwrap_err();
+
+        assert_eq!(check("gold == name"), "can't compare a int with a string");
+        assert_eq!(check("name > 1"), "can't compare a string with a int");
+        assert_eq!(
+            check("gold == yes"),
+            "can't compare a int with a true or false"
+        );
+        assert_eq!(
+            check("yes < no"),
+            "true or false can only be compared with == and !="
+        );
+        assert_eq!(
+            check("price"),
+            "the condition has to be true or false, but it's a float"
+        );
+        assert_eq!(
+            check("not gold"),
+            "'not' needs true or false, but got a int"
+        );
+        assert_eq!(
+            check("yes and name"),
+            "'and' needs true or false, but got a string"
+        );
+        assert_eq!(check("1 or yes"), "'or' needs true or false, but got a int");
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let parse = |source: &str| parse(source).unwrap_err();
+
+        assert_eq!(parse(" "), "the condition is empty");
+        assert_eq!(parse("gold >"), "the condition ends too soon");
+        assert_eq!(parse("gold = 1"), "use == to compare, at column 6");
+        assert_eq!(parse("gold $ 1"), "unexpected '$' at column 6");
+        assert_eq!(parse("1.2.3 == x"), "'1.2.3' at column 1 is not a number");
+        assert_eq!(parse("x == \"open"), "the text at column 6 is never closed");
+        assert_eq!(parse("a b"), "unexpected 'b' at column 3");
+        assert_eq!(parse("a and or b"), "unexpected 'or' at column 7");
+        assert_eq!(parse(") a"), "unexpected ')' at column 1");
+        assert_eq!(
+            parse("(a or b"),
+            "missing ')' at column 8, for the '(' before it"
+        );
+        assert_eq!(
+            parse("1 < gold < 10"),
+            "comparisons can't be chained, use 'and' between them (column 10)"
+        );
+        assert_eq!(
+            parse("flag(x)"),
+            "flag has to be followed by a name in quotes, like flag(\"name\"), at column 5"
+        );
+        assert_eq!(parse("flag(\"x\""), "missing ')' at column 9");
+    }
+
+    #[test]
+    fn unset_flags_only_matter_when_nothing_else_decides() {
+        assert_eq!(eval("unset", &[]), None);
+        assert_eq!(eval("not unset", &[]), None);
+        assert_eq!(eval("flag(\"unset\")", &[]), None);
+        assert_eq!(eval("unset == true", &[]), None);
+        assert_eq!(eval("unset and true", &[]), None);
+        assert_eq!(eval("unset or false", &[]), None);
+        assert_eq!(eval("unset and false", &[]), Some(false));
+        assert_eq!(eval("false and unset", &[]), Some(false));
+        assert_eq!(eval("unset or true", &[]), Some(true));
+        assert_eq!(eval("gold > 1 or unset", &[]), Some(true));
+        assert_eq!(eval("not (unset and gold > 9)", &[]), Some(true));
+        assert_eq!(eval("not (unset or gold > 9)", &[]), None);
+    }
+
+    #[test]
+    fn variables_hide_flags_with_the_same_name() {
+        assert_eq!(eval("gold > 1", &[("gold", false)]), Some(true));
+        assert_eq!(eval("flag(\"gold\")", &[("gold", false)]), Some(false));
+        assert_eq!(
+            parse("gold > 1 and has_key or flag(\"a b\")")
+                .unwrap()
+                .flags(&variables()),
+            vec!["has_key", "a b"]
+        );
+    }
+
+    #[test]
+    fn rename_only_changes_the_name() {
+        assert_eq!(rename("gold >= 10", "gold", "coins"), "coins >= 10");
+        assert_eq!(rename("gold>=10", "gold", "coins"), "coins>=10");
+        assert_eq!(
+            rename("golden and (gold < 2 or gold == 3)", "gold", "coins"),
+            "golden and (coins < 2 or coins == 3)"
+        );
+        assert_eq!(
+            rename("flag(\"gold\") and name == \"gold\"", "gold", "coins"),
+            "flag(\"gold\") and name == \"gold\""
+        );
+        assert_eq!(rename("göld == 1 and gold", "gold", "g"), "göld == 1 and g");
+        assert_eq!(rename("göld == 1 and x", "göld", "g"), "g == 1 and x");
+        // Can't tell what's quoted when the quotes aren't closed
+        assert_eq!(rename("gold == \"open", "gold", "coins"), "gold == \"open");
+    }
+
+    #[test]
+    fn names_are_words_that_arent_keywords() {
+        for text in ["gold", "_x", "has_key2", "ünd", "andy", "flags"] {
+            assert!(is_name(text), "{}", text);
+        }
+        for text in ["", "my var", "1x", "and", "flag", "true", "a-b", "a.b"] {
+            assert!(!is_name(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn flag_references_read_back_as_the_same_flag() {
+        let names = vec!["gold".to_string()];
+        for (flag, written) in [
+            ("has_key", "has_key"),
+            ("gold", "flag(\"gold\")"),
+            ("and", "flag(\"and\")"),
+            ("1st", "flag(\"1st\")"),
+            ("my flag", "flag(\"my flag\")"),
+            ("a\"b\\", "flag(\"a\\\"b\\\\\")"),
+        ] {
+            assert_eq!(flag_reference(flag, &names), written);
+            let read = parse(written).unwrap();
+            assert_eq!(read.flags(&variables()), vec![flag], "{}", written);
+        }
+    }
+}
@@ -0,0 +1,224 @@
+// Undo/redo made of whole snapshots of whatever is being edited. Edits that take many frames
+// (dragging, typing) use begin_edit/end_edit so they end up as a single entry.
+pub struct History<T: Clone + PartialEq> {
+    undo_stack: Vec<T>,
+    redo_stack: Vec<T>,
+    pending: Option<T>, // state from before the edit that is still going on
+    max_depth: usize,
+}
+
+impl<T: Clone + PartialEq> History<T> {
+    pub fn new(max_depth: usize) -> History<T> {
+        History {
+            undo_stack: vec![],
+            redo_stack: vec![],
+            pending: None,
+            max_depth,
+        }
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+        self.trim();
+    }
+
+    // Call right before changing anything, with the state as it is before the change
+    pub fn record(&mut self, before: T) {
+        self.pending = None;
+        self.undo_stack.push(before);
+        self.redo_stack.clear();
+        self.trim();
+    }
+
+    pub fn begin_edit(&mut self, before: T) {
+        if self.pending.is_none() {
+            self.pending = Some(before);
+        }
+    }
+
+    // Nothing is recorded if the edit ended up not changing anything
+    pub fn end_edit(&mut self, after: &T) {
+        match self.pending.take() {
+            Some(before) if before != *after => self.record(before),
+            _ => {}
+        }
+    }
+
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        self.pending = None;
+        let to_return = self.undo_stack.pop()?;
+        self.redo_stack.push(current);
+        Some(to_return)
+    }
+
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        self.pending = None;
+        let to_return = self.redo_stack.pop()?;
+        self.undo_stack.push(current);
+        self.trim();
+        Some(to_return)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.pending = None;
+    }
+
+    fn trim(&mut self) {
+        if self.undo_stack.len() > self.max_depth {
+            let extra = self.undo_stack.len() - self.max_depth;
+            self.undo_stack.drain(0..extra);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Edits a number, recording it like the editor records the document
+    fn change(history: &mut History<i32>, current: &mut i32, to: i32) {
+        history.record(*current);
+        *current = to;
+    }
+
+    fn undo(history: &mut History<i32>, current: &mut i32) -> bool {
+        match history.undo(*current) {
+            Some(before) => {
+                *current = before;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn redo(history: &mut History<i32>, current: &mut i32) -> bool {
+        match history.redo(*current) {
+            Some(after) => {
+                *current = after;
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_go_back_and_forth_in_order() {
+        let mut history = History::new(10);
+        let mut current = 0;
+        change(&mut history, &mut current, 1);
+        change(&mut history, &mut current, 2);
+        change(&mut history, &mut current, 3);
+
+        assert!(undo(&mut history, &mut current));
+        assert_eq!(current, 2);
+        assert!(undo(&mut history, &mut current));
+        assert_eq!(current, 1);
+        assert!(redo(&mut history, &mut current));
+        assert_eq!(current, 2);
+        assert!(undo(&mut history, &mut current));
+        assert!(undo(&mut history, &mut current));
+        assert_eq!(current, 0);
+        assert!(!undo(&mut history, &mut current));
+        assert_eq!(current, 0);
+
+        for expected in [1, 2, 3] {
+            assert!(redo(&mut history, &mut current));
+            assert_eq!(current, expected);
+        }
+        assert!(!redo(&mut history, &mut current));
+        assert_eq!(current, 3);
+    }
+
+    #[test]
+    fn a_new_change_clears_redo() {
+        let mut history = History::new(10);
+        let mut current = 0;
+        change(&mut history, &mut current, 1);
+        change(&mut history, &mut current, 2);
+        undo(&mut history, &mut current);
+        change(&mut history, &mut current, 5);
+
+        assert!(!redo(&mut history, &mut current));
+        assert_eq!(current, 5);
+        undo(&mut history, &mut current);
+        assert_eq!(current, 1);
+    }
+
+    #[test]
+    fn edits_over_many_frames_are_one_entry() {
+        let mut history = History::new(10);
+        let mut current = 0;
+        history.begin_edit(current);
+        for frame in 1..=5 {
+            // Called every frame, only the first one counts
+            history.begin_edit(current);
+            current = frame * 10;
+        }
+        history.end_edit(&current);
+        assert_eq!(current, 50);
+
+        assert!(undo(&mut history, &mut current));
+        assert_eq!(current, 0);
+        assert!(!undo(&mut history, &mut current));
+        assert!(redo(&mut history, &mut current));
+        assert_eq!(current, 50);
+    }
+
+    #[test]
+    fn edits_that_change_nothing_are_not_recorded() {
+        let mut history = History::new(10);
+        let mut current = 0;
+        change(&mut history, &mut current, 1);
+        undo(&mut history, &mut current);
+
+        // Dragged away and back to where it was
+        history.begin_edit(current);
+        current = 7;
+        current -= 7;
+        history.end_edit(&current);
+
+        assert!(!undo(&mut history, &mut current));
+        assert!(redo(&mut history, &mut current), "redo is still there");
+        assert_eq!(current, 1);
+        // Ending without beginning does nothing either
+        history.end_edit(&current);
+        undo(&mut history, &mut current);
+        assert!(!undo(&mut history, &mut current));
+    }
+
+    #[test]
+    fn only_the_newest_entries_are_kept() {
+        let mut history = History::new(3);
+        let mut current = 0;
+        for to in 1..=5 {
+            change(&mut history, &mut current, to);
+        }
+        let mut undone = vec![];
+        while undo(&mut history, &mut current) {
+            undone.push(current);
+        }
+        assert_eq!(undone, [4, 3, 2]);
+
+        // Lowering the depth drops the oldest ones right away
+        while redo(&mut history, &mut current) {}
+        history.set_max_depth(1);
+        assert!(undo(&mut history, &mut current));
+        assert_eq!(current, 4);
+        assert!(!undo(&mut history, &mut current));
+
+        // Redoing pushes onto undo too, that stays within the depth
+        let mut history = History::new(2);
+        let mut current = 0;
+        for to in 1..=2 {
+            change(&mut history, &mut current, to);
+        }
+        while undo(&mut history, &mut current) {}
+        history.set_max_depth(1);
+        while redo(&mut history, &mut current) {}
+        assert_eq!(current, 2);
+        assert!(undo(&mut history, &mut current));
+        assert!(!undo(&mut history, &mut current));
+    }
+}
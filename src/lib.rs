@@ -0,0 +1,17 @@
+// The dialogue graph and its file format, without anything from raylib so it can be used by
+// tools, tests and game runtimes. The editor itself is the dialogue_maker binary.
+
+pub mod document;
+pub mod expression;
+pub mod history;
+pub mod migrations;
+pub mod node;
+pub mod text_edit;
+pub mod validate;
+pub mod variables;
+
+pub use document::{CameraLayout, CardLayout, Document, EditorLayout, LoadError};
+pub use migrations::FORMAT_VERSION;
+pub use node::{Node, NodeMember, NodeTypes, RandomMode};
+pub use validate::{validate, Problem, Severity};
+pub use variables::{Operation, Value, VarType, Variable};
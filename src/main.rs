@@ -1,176 +1,180 @@
 #![allow(unreachable_patterns)]
 
-use std::collections::HashMap;
-
-use json_parser::{JsonObject, JsonType, Parser};
-use raylib::{input::key_from_i32, prelude::*};
-
-mod json_parser;
+use std::collections::{HashMap, HashSet};
+
+use dialogue_maker::document::{self, CameraLayout, CardLayout, Document, EditorLayout};
+use dialogue_maker::expression;
+use dialogue_maker::history::History;
+use dialogue_maker::text_edit::{self, TextEdit};
+use dialogue_maker::{
+    validate, Node, NodeMember, NodeTypes, Operation, Problem, RandomMode, Severity, Value,
+    Variable,
+};
+use raylib::prelude::*;
 
 #[derive(Debug)]
 enum CanvasMouseState {
     Roaming,
     CreatingConnection(String, usize), //id, output_index
-    MovingCard(String),
-}
-
-#[derive(Clone, Debug, PartialEq)]
-enum NodeTypes {
-    Dialogue,
-    Branches,
-    SetFlag,
-    Conditional, // Noticed I didn't think enough about this one, decide to make it so that flags are only flags
-    EmitEvent,
-    SomethingHasGoneReallyWrong,
+    MovingCard(String),                // the grabbed card, all the selected ones move with it
+    BoxSelecting(Vector2),             // where the box started, in world coordinates
+    PickingJumpTarget(String),         // the jump, letting go over a card makes it the target
 }
 
-impl Default for NodeTypes {
-    fn default() -> NodeTypes {
-        NodeTypes::SomethingHasGoneReallyWrong
-    }
-}
+// Note: Cards and widgets will be references to nodes, nodes will not have access to anything related to cards and widgets, but cards and widgets will have knowledge of nodes
 
-#[derive(Debug, Clone)]
-enum NodeMember {
-    Character,
-    Dialogue,
-    Branch(usize),
-    FlagToCheck,
-    FlagToSet,
-    ValueToSet,
-    EventToEmit,
-    EventDataKey(usize),
-    EventDataVal(usize),
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum WidgetType {
+    TextInput,
+    TextArea { lines: usize }, // grows with the text, for long dialogue lines
+    CheckBox,
+    Selector, // shows the current choice, clicking goes to the next one
+    OutputConnection,
 }
-
-#[derive(Default, Clone)]
-struct Node {
-    id: String,
-    character: Option<String>,
-    dialogue: Option<String>,
-    branches: Option<Vec<String>>,
-    flag_to_check: Option<String>,
-    flag_to_set: Option<String>,
-    value_to_set: Option<bool>,
-    front_links: Vec<String>, // Vector of other Nodes' ids
-    event_to_emit: Option<String>,
-    event_data: Option<Vec<(String, String)>>, // TODO?: Maybe integrate the JsonType from my json parser
-    node_type: NodeTypes,
+// Size glyphs are rendered at when loading a font, they get scaled to whatever size is drawn
+const FONT_LOAD_SIZE: i32 = 48;
+
+// Text is drawn with the TTF font from the DIALOGUE_MAKER_FONT env variable when it's set. Only the
+// chars the project uses get loaded, since fonts for languages like Japanese have tens of
+// thousands, so it gets loaded again whenever new ones show up.
+struct EditorFont {
+    path: Option<String>,
+    font: Option<Font>, // None draws with raylib's default font, which is only ascii
+    codepoints: Vec<char>, // the ones the font was loaded with and the ones waiting to be
+    needs_reload: bool,
 }
 
-impl Node {
-    fn default_dialogue() -> Node {
-        let mut to_return = Node::default();
-        to_return.character = Some("".to_string());
-        to_return.dialogue = Some("".to_string());
-        to_return.front_links = vec![];
-        to_return.node_type = NodeTypes::Dialogue;
-        to_return
-    }
-    fn new_dialogue<T: ToString>(
-        id: T,
-        character: T,
-        dialogue: T,
-        front_links: Vec<String>,
-    ) -> Node {
-        let mut to_return = Node::default_dialogue();
-        to_return.id = id.to_string();
-        to_return.character = Some(character.to_string());
-        to_return.dialogue = Some(dialogue.to_string());
-        to_return.front_links = front_links;
-        to_return
+impl EditorFont {
+    fn new(path: Option<String>) -> EditorFont {
+        EditorFont {
+            needs_reload: path.is_some(),
+            path,
+            font: None,
+            codepoints: (' '..='~').collect(),
+        }
     }
 
-    fn default_branches() -> Node {
-        let mut to_return = Node::default();
-        to_return.branches = Some(vec![]);
-        to_return.front_links = vec![];
-        to_return.node_type = NodeTypes::Branches;
-        to_return
-    }
-    fn new_branches<T: ToString>(id: T, branches: Vec<String>, front_links: Vec<String>) -> Node {
-        let mut to_return = Node::default_branches();
-        to_return.id = id.to_string();
-        to_return.branches = Some(branches);
-        to_return.front_links = front_links;
-        to_return
+    fn add_chars(&mut self, text: &str) {
+        if self.path.is_none() {
+            return;
+        }
+        for c in text.chars() {
+            if !c.is_control() && !self.codepoints.contains(&c) {
+                self.codepoints.push(c);
+                self.needs_reload = true;
+            }
+        }
     }
 
-    fn default_conditional() -> Node {
-        let mut to_return = Node::default();
-        to_return.flag_to_check = Some("".to_string());
-        to_return.front_links = vec!["".to_string(), "".to_string(), "".to_string()];
-        to_return.node_type = NodeTypes::Conditional;
-        to_return
+    fn add_node_chars(&mut self, node: &Node) {
+        let texts = [
+            &node.character,
+            &node.dialogue,
+            &node.condition,
+            &node.flag_to_set,
+            &node.event_to_emit,
+            &node.entry_name,
+            &node.variable,
+            &node.variable_value,
+        ];
+        for text in texts.into_iter().flatten() {
+            self.add_chars(text);
+        }
+        for branch in node.branches.iter().flatten() {
+            self.add_chars(branch);
+        }
+        for condition in node.branch_conditions.iter().flatten() {
+            self.add_chars(condition);
+        }
+        for (key, val) in node.event_data.iter().flatten() {
+            self.add_chars(key);
+            self.add_chars(val);
+        }
     }
-    fn new_conditional<T: ToString>(id: T, flag_to_check: T, front_links: Vec<String>) -> Node {
-        let mut to_return = Node::default_conditional();
-        to_return.id = id.to_string();
-        to_return.flag_to_check = Some(flag_to_check.to_string());
 
-        if front_links.len() != 3 {
-            println!("ERROR: New_conditional front_links parameter should have a lenght of 3, setting to default");
-            to_return.front_links = vec!["".to_string(), "".to_string(), "".to_string()];
-        } else {
-            to_return.front_links = front_links;
+    // Returns true if the font changed, text can have a different size with the new one
+    fn reload_if_needed(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) -> bool {
+        if !self.needs_reload {
+            return false;
         }
+        self.needs_reload = false;
 
-        to_return
-    }
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => return false,
+        };
 
-    fn default_set_flag() -> Node {
-        let mut to_return = Node::default();
-        to_return.flag_to_set = Some("".to_string());
-        to_return.value_to_set = Some(false);
-        to_return.front_links = vec![];
-        to_return.node_type = NodeTypes::SetFlag;
-        to_return
-    }
-    fn new_set_flag<T: ToString>(
-        id: T,
-        flag_to_set: T,
-        value_to_set: bool,
-        front_links: Vec<String>,
-    ) -> Node {
-        let mut to_return = Node::default_set_flag();
-        to_return.id = id.to_string();
-        to_return.flag_to_set = Some(flag_to_set.to_string());
-        to_return.value_to_set = Some(value_to_set);
-        to_return.front_links = front_links;
-        to_return
+        let codepoints: Vec<i32> = self.codepoints.iter().map(|c| *c as i32).collect();
+        match rl.load_font_ex(
+            thread,
+            &path,
+            FONT_LOAD_SIZE,
+            FontLoadEx::Chars(&codepoints),
+        ) {
+            Ok(font) => {
+                font.texture()
+                    .set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_BILINEAR);
+                self.font = Some(font);
+            }
+            Err(err) => {
+                // No point in trying again with every new char
+                println!("FONT_ERR: Could not load '{}', {}", path, err);
+                self.path = None;
+            }
+        }
+
+        true
     }
 
-    fn default_emit_event() -> Node {
-        let mut to_return = Node::default();
-        to_return.event_to_emit = Some("".to_string());
-        to_return.node_type = NodeTypes::EmitEvent;
-        to_return
+    fn draw_text(
+        &self,
+        d: &mut impl RaylibDraw,
+        text: &str,
+        x: i32,
+        y: i32,
+        size: i32,
+        color: Color,
+    ) {
+        match &self.font {
+            Some(font) => d.draw_text_ex(
+                font,
+                text,
+                Vector2 {
+                    x: x as f32,
+                    y: y as f32,
+                },
+                size as f32,
+                0.,
+                color,
+            ),
+            None => d.draw_text(text, x, y, size, color),
+        }
     }
-    fn new_emit_event<T: ToString>(
-        id: T,
-        event_to_emit: T,
-        event_data: Vec<(String, String)>,
-        front_links: Vec<String>,
-    ) -> Node {
-        let mut to_return = Node::default_emit_event();
-        to_return.id = id.to_string();
-        to_return.event_to_emit = Some(event_to_emit.to_string());
-        to_return.event_data = Some(event_data);
-        to_return.front_links = front_links;
-        to_return
+
+    fn measure_text(&self, text: &str, size: i32) -> i32 {
+        match &self.font {
+            Some(font) => measure_text_ex(font, text, size as f32, 0.).x as i32,
+            None => measure_text(text, size),
+        }
     }
 }
 
-// Note: Cards and widgets will be references to nodes, nodes will not have access to anything related to cards and widgets, but cards and widgets will have knowledge of nodes
+const TEXT_AREA_FONT_SIZE: i32 = 19;
+const TEXT_AREA_LINE_HEIGHT: i32 = 20;
+const TEXT_AREA_TEXT_WIDTH: i32 = 144;
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum WidgetType {
-    TextInput,
-    CheckBox,
-    OutputConnection,
+fn text_area_height(lines: usize) -> i32 {
+    lines as i32 * TEXT_AREA_LINE_HEIGHT + 5
+}
+
+fn wrap_text_area(text: &str, font: &EditorFont) -> Vec<(usize, usize)> {
+    text_edit::wrap_text(text, TEXT_AREA_TEXT_WIDTH, |line| {
+        font.measure_text(line, TEXT_AREA_FONT_SIZE)
+    })
 }
+
 // TODO: Implement outputs
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct Widget {
     node_ref: String,
     widget_type: WidgetType,
@@ -183,6 +187,7 @@ impl Widget {
     fn draw(
         &self,
         d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+        font: &EditorFont,
         card_world_pos: Vector2,
         text: Option<String>,
         check_box_state: Option<bool>,
@@ -193,12 +198,40 @@ impl Widget {
             WidgetType::TextInput => {
                 d.draw_rectangle(x_pos, y_pos, 150, 25, Color::GRAY);
                 d.draw_rectangle(x_pos + 1, y_pos + 1, 148, 23, Color::WHITE);
+                // Cut by the drawn width, counting bytes or chars cut wide text too late
                 let mut text_to_show = text.unwrap();
-                if text_to_show.len() > 14 {
-                    text_to_show = text_to_show.chars().take(14).collect();
+                if font.measure_text(&text_to_show, 19) > 144 {
+                    while font.measure_text(&format!("{}...", text_to_show), 19) > 144 {
+                        text_to_show.pop();
+                    }
                     text_to_show.push_str("...");
                 }
-                d.draw_text(&text_to_show, x_pos + 3, y_pos + 3, 19, Color::BLACK)
+                font.draw_text(d, &text_to_show, x_pos + 3, y_pos + 3, 19, Color::BLACK)
+            }
+            WidgetType::TextArea { lines } => {
+                let height = text_area_height(lines);
+                d.draw_rectangle(x_pos, y_pos, 150, height, Color::GRAY);
+                d.draw_rectangle(x_pos + 1, y_pos + 1, 148, height - 2, Color::WHITE);
+
+                let text = text.unwrap();
+                // The card may not have been fitted to the text yet
+                for (i, (start, end)) in wrap_text_area(&text, font).iter().take(lines).enumerate()
+                {
+                    font.draw_text(
+                        d,
+                        &text[*start..*end],
+                        x_pos + 3,
+                        y_pos + 3 + i as i32 * TEXT_AREA_LINE_HEIGHT,
+                        TEXT_AREA_FONT_SIZE,
+                        Color::BLACK,
+                    );
+                }
+            }
+            WidgetType::Selector => {
+                d.draw_rectangle(x_pos, y_pos, 150, 25, Color::GRAY);
+                d.draw_rectangle(x_pos + 1, y_pos + 1, 148, 23, Color::LIGHTGRAY);
+                font.draw_text(d, &text.unwrap(), x_pos + 3, y_pos + 3, 19, Color::BLACK);
+                font.draw_text(d, ">", x_pos + 136, y_pos + 3, 19, Color::DARKGRAY);
             }
             WidgetType::OutputConnection => d.draw_circle(x_pos, y_pos, 10., Color::GREEN),
             WidgetType::CheckBox => {
@@ -216,7 +249,7 @@ impl Widget {
                     false => "False",
                 };
 
-                d.draw_text(text, x_pos + 35, y_pos, 25, Color::BLACK);
+                font.draw_text(d, text, x_pos + 35, y_pos, 25, Color::BLACK);
             }
             _ => unimplemented!("{:?}", self.widget_type),
         }
@@ -229,7 +262,11 @@ impl Widget {
         };
 
         let size = match self.widget_type {
-            WidgetType::TextInput => Vector2 { x: 150., y: 25. },
+            WidgetType::TextInput | WidgetType::Selector => Vector2 { x: 150., y: 25. },
+            WidgetType::TextArea { lines } => Vector2 {
+                x: 150.,
+                y: text_area_height(lines) as f32,
+            },
             WidgetType::OutputConnection => Vector2 { x: 20., y: 20. },
             WidgetType::CheckBox => Vector2 { x: 25., y: 25. },
             _ => unimplemented!("{:?}", self.widget_type),
@@ -249,7 +286,7 @@ impl Widget {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct Card {
     node_ref: String,
     pos: Vector2,
@@ -258,12 +295,21 @@ struct Card {
     card_type: NodeTypes,
 }
 
+// Where the parts of a jump card that aren't widgets are, from its top left
+const JUMP_PICKER_OFFSET: Vector2 = Vector2 { x: 170., y: 57. };
+const JUMP_GO_TO_OFFSET: Vector2 = Vector2 { x: 120., y: 10. };
+const JUMP_GO_TO_SIZE: Vector2 = Vector2 { x: 40., y: 25. };
+
 #[derive(Debug)]
 enum CardNotification {
     EditTextInput { id: String, node_member: NodeMember },
     AddBranchToBranchesNode(String),
     AddArgToEmitEventNode(String),
+    AddOutputToRandomNode(String),
+    PickingJumpTarget(String),
+    GoToJumpTarget(String),
     ToggleCheckBox { id: String, node_member: NodeMember },
+    CycleSelector { id: String, node_member: NodeMember },
     CreatingCardConnection(String, usize), // id, output index
     MovingCard(String),
 }
@@ -283,7 +329,7 @@ impl Card {
                 },
                 Widget {
                     node_ref: node_id.clone(),
-                    widget_type: WidgetType::TextInput,
+                    widget_type: WidgetType::TextArea { lines: 1 },
                     offset: Vector2 { x: 10., y: 115. },
                     editing_node_member: Some(NodeMember::Dialogue),
                 },
@@ -304,6 +350,7 @@ impl Card {
         let mut offset_y = 10.;
         let mut cur_i = 0;
 
+        // Each branch is its text, the condition to show it and what to do when it's false
         for _ in branches {
             branches_widgets.push(Widget {
                 node_ref: node_id.clone(),
@@ -323,7 +370,25 @@ impl Card {
                 },
                 editing_node_member: None,
             });
-            offset_y += 35.;
+            branches_widgets.push(Widget {
+                node_ref: node_id.clone(),
+                widget_type: WidgetType::TextInput,
+                offset: Vector2 {
+                    x: 10.,
+                    y: offset_y + 30.,
+                },
+                editing_node_member: Some(NodeMember::BranchCondition(cur_i)),
+            });
+            branches_widgets.push(Widget {
+                node_ref: node_id.clone(),
+                widget_type: WidgetType::Selector,
+                offset: Vector2 {
+                    x: 10.,
+                    y: offset_y + 60.,
+                },
+                editing_node_member: Some(NodeMember::BranchShowDisabled(cur_i)),
+            });
+            offset_y += 100.;
             cur_i += 1;
         }
 
@@ -344,7 +409,7 @@ impl Card {
             node_ref: node_id.clone(),
             pos: pos,
             size: Vector2 { x: 170., y: 185. },
-            // flag to check
+            // condition
             // if true ->
             // if false ->
             // wasn't set ->
@@ -352,7 +417,7 @@ impl Card {
                 Widget {
                     node_ref: node_id.clone(),
                     widget_type: WidgetType::TextInput,
-                    editing_node_member: Some(NodeMember::FlagToCheck),
+                    editing_node_member: Some(NodeMember::Condition),
                     offset: Vector2 { x: 10., y: 10. },
                 },
                 Widget {
@@ -464,6 +529,198 @@ impl Card {
         }
     }
 
+    fn new_start(node_id: String, pos: Vector2) -> Card {
+        Card {
+            node_ref: node_id.clone(),
+            pos,
+            size: Vector2 { x: 170., y: 80. },
+            widgets: vec![
+                Widget {
+                    node_ref: node_id.clone(),
+                    widget_type: WidgetType::TextInput,
+                    editing_node_member: Some(NodeMember::EntryName),
+                    offset: Vector2 { x: 10., y: 45. },
+                },
+                Widget {
+                    node_ref: node_id.clone(),
+                    widget_type: WidgetType::OutputConnection,
+                    editing_node_member: None,
+                    offset: Vector2 { x: 170., y: 57. },
+                },
+            ],
+            card_type: NodeTypes::Start,
+        }
+    }
+
+    fn new_end(node_id: String, pos: Vector2) -> Card {
+        Card {
+            node_ref: node_id.clone(),
+            pos,
+            size: Vector2 { x: 170., y: 80. },
+            widgets: vec![Widget {
+                node_ref: node_id.clone(),
+                widget_type: WidgetType::TextInput,
+                editing_node_member: Some(NodeMember::ExitCode),
+                offset: Vector2 { x: 10., y: 45. },
+            }],
+            card_type: NodeTypes::End,
+        }
+    }
+
+    fn new_set_variable(node_id: String, pos: Vector2) -> Card {
+        Card {
+            node_ref: node_id.clone(),
+            pos,
+            size: Vector2 { x: 170., y: 115. },
+            widgets: vec![
+                Widget {
+                    node_ref: node_id.clone(),
+                    widget_type: WidgetType::TextInput,
+                    editing_node_member: Some(NodeMember::Variable),
+                    offset: Vector2 { x: 10., y: 10. },
+                },
+                Widget {
+                    node_ref: node_id.clone(),
+                    widget_type: WidgetType::Selector,
+                    editing_node_member: Some(NodeMember::Operation),
+                    offset: Vector2 { x: 10., y: 45. },
+                },
+                Widget {
+                    node_ref: node_id.clone(),
+                    widget_type: WidgetType::TextInput,
+                    editing_node_member: Some(NodeMember::VariableValue),
+                    offset: Vector2 { x: 10., y: 80. },
+                },
+                Widget {
+                    node_ref: node_id.clone(),
+                    widget_type: WidgetType::OutputConnection,
+                    editing_node_member: None,
+                    offset: Vector2 { x: 170., y: 92. },
+                },
+            ],
+            card_type: NodeTypes::SetVariable,
+        }
+    }
+
+    fn new_random(node_id: String, outputs: usize, pos: Vector2) -> Card {
+        // mode
+        // weight ->, one per output
+        let mut widgets = vec![Widget {
+            node_ref: node_id.clone(),
+            widget_type: WidgetType::Selector,
+            editing_node_member: Some(NodeMember::RandomMode),
+            offset: Vector2 { x: 10., y: 10. },
+        }];
+
+        let mut offset_y = 45.;
+        for i in 0..outputs {
+            widgets.push(Widget {
+                node_ref: node_id.clone(),
+                widget_type: WidgetType::TextInput,
+                editing_node_member: Some(NodeMember::Weight(i)),
+                offset: Vector2 {
+                    x: 10.,
+                    y: offset_y,
+                },
+            });
+            widgets.push(Widget {
+                node_ref: node_id.clone(),
+                widget_type: WidgetType::OutputConnection,
+                editing_node_member: None,
+                offset: Vector2 {
+                    x: 170.,
+                    y: offset_y + 10.,
+                },
+            });
+            offset_y += 35.;
+        }
+
+        Card {
+            node_ref: node_id.clone(),
+            pos,
+            size: Vector2 {
+                x: 170.,
+                y: offset_y,
+            },
+            widgets,
+            card_type: NodeTypes::Random,
+        }
+    }
+
+    fn new_jump(node_id: String, pos: Vector2) -> Card {
+        // Jump to: go
+        // target  o <- dragged to a card to pick it, no connection is drawn
+        Card {
+            node_ref: node_id.clone(),
+            pos,
+            size: Vector2 { x: 170., y: 80. },
+            widgets: vec![Widget {
+                node_ref: node_id.clone(),
+                widget_type: WidgetType::TextInput,
+                editing_node_member: Some(NodeMember::JumpTarget),
+                offset: Vector2 { x: 10., y: 45. },
+            }],
+            card_type: NodeTypes::Jump,
+        }
+    }
+
+    fn from_node(node: &Node, pos: Vector2) -> Card {
+        match node.node_type {
+            NodeTypes::Dialogue => Card::new_dialogue(node.id.clone(), pos),
+            NodeTypes::Branches => {
+                Card::new_branches(node.id.clone(), node.branches.clone().unwrap(), pos)
+            }
+            NodeTypes::Conditional => Card::new_conditional(node.id.clone(), pos),
+            NodeTypes::SetFlag => Card::new_set_flag(node.id.clone(), pos),
+            NodeTypes::EmitEvent => {
+                Card::new_emit_event(node.id.clone(), node.event_data.clone().unwrap(), pos)
+            }
+            NodeTypes::Start => Card::new_start(node.id.clone(), pos),
+            NodeTypes::End => Card::new_end(node.id.clone(), pos),
+            NodeTypes::SetVariable => Card::new_set_variable(node.id.clone(), pos),
+            NodeTypes::Random => {
+                Card::new_random(node.id.clone(), node.weights.clone().unwrap().len(), pos)
+            }
+            NodeTypes::Jump => Card::new_jump(node.id.clone(), pos),
+            _ => unimplemented!("{:?}", node.node_type),
+        }
+    }
+
+    // Sizes the card for what's in it, heights saved in the layout can be from before a card
+    // got more rows so they're never trusted for these
+    fn fit_to_content(&mut self, node_data: &Node, font: &EditorFont) {
+        match self.card_type {
+            NodeTypes::Dialogue => {
+                let lines = wrap_text_area(&node_data.dialogue.clone().unwrap(), font).len();
+                self.widgets[1].widget_type = WidgetType::TextArea { lines };
+
+                let bottom = self.widgets[1].offset.y + text_area_height(lines) as f32;
+                self.widgets[2].offset.y = bottom;
+                self.size.y = bottom + 10.;
+            }
+            // One row per branch, output or argument, and jumps used to be taller
+            NodeTypes::Branches | NodeTypes::Random | NodeTypes::EmitEvent | NodeTypes::Jump => {
+                self.size.y = Card::from_node(node_data, self.pos).size.y;
+            }
+            _ => {}
+        }
+    }
+
+    fn overlaps(&self, top_left: Vector2, bottom_right: Vector2) -> bool {
+        self.pos.x < bottom_right.x
+            && self.pos.x + self.size.x > top_left.x
+            && self.pos.y - 12. < bottom_right.y
+            && self.pos.y + self.size.y > top_left.y
+    }
+
+    // Header included
+    fn contains(&self, pos: Vector2) -> bool {
+        pos.x > self.pos.x - 12.
+            && pos.x < self.pos.x + self.size.x + 12.
+            && pos.y > self.pos.y - 12.
+            && pos.y < self.pos.y + self.size.y
+    }
+
     fn copy_output_widgets(&self) -> Vec<Widget> {
         self.widgets
             .iter()
@@ -514,7 +771,13 @@ impl Card {
                                 node_member: wid.editing_node_member.clone().unwrap(),
                             });
                         }
-                        WidgetType::TextInput => {
+                        WidgetType::Selector => {
+                            return Some(CardNotification::CycleSelector {
+                                id: wid.node_ref.clone(),
+                                node_member: wid.editing_node_member.clone().unwrap(),
+                            });
+                        }
+                        WidgetType::TextInput | WidgetType::TextArea { .. } => {
                             return Some(CardNotification::EditTextInput {
                                 id: wid.node_ref.clone(),
                                 node_member: wid.editing_node_member.clone().unwrap(),
@@ -558,6 +821,32 @@ impl Card {
                         ));
                     }
                 }
+                NodeTypes::Random => {
+                    let add_button_center = Vector2 {
+                        x: self.pos.x + self.size.x / 2.,
+                        y: self.pos.y + self.size.y,
+                    };
+
+                    if mouse_world_pos.distance_to(add_button_center) < 10. {
+                        return Some(CardNotification::AddOutputToRandomNode(
+                            self.node_ref.clone(),
+                        ));
+                    }
+                }
+                NodeTypes::Jump => {
+                    if mouse_world_pos.distance_to(self.pos + JUMP_PICKER_OFFSET) < 10. {
+                        return Some(CardNotification::PickingJumpTarget(self.node_ref.clone()));
+                    }
+
+                    let go_to = self.pos + JUMP_GO_TO_OFFSET;
+                    if mouse_world_pos.x > go_to.x
+                        && mouse_world_pos.x < go_to.x + JUMP_GO_TO_SIZE.x
+                        && mouse_world_pos.y > go_to.y
+                        && mouse_world_pos.y < go_to.y + JUMP_GO_TO_SIZE.y
+                    {
+                        return Some(CardNotification::GoToJumpTarget(self.node_ref.clone()));
+                    }
+                }
                 _ => {}
             }
         }
@@ -565,52 +854,101 @@ impl Card {
         None
     }
 
-    fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, node_data: Node) {
-        self.draw_card_bg(d);
+    fn draw(
+        &self,
+        d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+        font: &EditorFont,
+        node_data: Node,
+        nodes: &[Node],
+        variables: &[Variable],
+        selected: bool,
+    ) {
+        self.draw_card_bg(d, selected);
         match self.card_type {
             NodeTypes::Dialogue => {
-                self.draw_lable(d, "Character:", Vector2 { x: 10., y: 10. });
-                self.widgets[0].draw(d, self.pos, node_data.character, None);
-                self.draw_lable(d, "Dialogue:", Vector2 { x: 10., y: 80. });
-                self.widgets[1].draw(d, self.pos, node_data.dialogue, None);
-                self.widgets[2].draw(d, self.pos, None, None)
+                self.draw_lable(d, font, "Character:", Vector2 { x: 10., y: 10. });
+                self.widgets[0].draw(d, font, self.pos, node_data.character, None);
+                self.draw_lable(d, font, "Dialogue:", Vector2 { x: 10., y: 80. });
+                self.widgets[1].draw(d, font, self.pos, node_data.dialogue, None);
+                self.widgets[2].draw(d, font, self.pos, None, None)
             }
             NodeTypes::Branches => {
-                let mut cur_opt_i = 0;
                 for i in &self.widgets {
-                    match i.widget_type {
-                        WidgetType::TextInput => {
-                            let cur_opt_vec = node_data.branches.clone().unwrap();
-                            let cur_opt_text = cur_opt_vec[cur_opt_i].clone();
-                            i.draw(d, self.pos, Some(cur_opt_text), None);
-                            cur_opt_i += 1;
+                    match i.editing_node_member {
+                        Some(NodeMember::Branch(opt_i)) => {
+                            let cur_opt_text = node_data.branches.as_ref().unwrap()[opt_i].clone();
+                            i.draw(d, font, self.pos, Some(cur_opt_text), None);
                         }
-                        WidgetType::OutputConnection => {
-                            i.draw(d, self.pos, None, None);
+                        Some(NodeMember::BranchCondition(opt_i)) => {
+                            let condition =
+                                node_data.branch_conditions.as_ref().unwrap()[opt_i].clone();
+                            i.draw(d, font, self.pos, Some(condition.clone()), None);
+
+                            let x_pos = (self.pos.x + i.offset.x) as i32;
+                            let y_pos = (self.pos.y + i.offset.y) as i32;
+                            if condition.trim().is_empty() {
+                                font.draw_text(
+                                    d,
+                                    "if: always",
+                                    x_pos + 3,
+                                    y_pos + 3,
+                                    19,
+                                    Color::GRAY,
+                                );
+                            } else if expression::check_condition(&condition, variables).is_err() {
+                                // The reason is in the problems panel, there's no room for it here
+                                d.draw_rectangle_lines(x_pos - 1, y_pos - 1, 152, 27, Color::RED);
+                            }
                         }
-                        _ => unimplemented!("{:?}", i.widget_type),
+                        Some(NodeMember::BranchShowDisabled(opt_i)) => {
+                            let text = match node_data.branch_show_disabled.as_ref().unwrap()[opt_i]
+                            {
+                                true => "Else: disable",
+                                false => "Else: hide",
+                            };
+                            i.draw(d, font, self.pos, Some(text.to_string()), None);
+                        }
+                        _ => i.draw(d, font, self.pos, None, None),
                     }
                 }
             }
             NodeTypes::Conditional => {
+                let condition = node_data.condition.clone().unwrap();
                 for i in &self.widgets {
-                    i.draw(
-                        d,
-                        self.pos,
-                        Some(node_data.flag_to_check.clone().unwrap()),
-                        None,
-                    );
+                    i.draw(d, font, self.pos, Some(condition.clone()), None);
                 }
 
-                self.draw_lable(d, "Branches:", Vector2 { x: 10., y: 45. });
-                self.draw_lable(d, "If true:", Vector2 { x: 10., y: 80. });
-                self.draw_lable(d, "If false:", Vector2 { x: 10., y: 115. });
-                self.draw_lable(d, "If not set:", Vector2 { x: 10., y: 150. });
+                // Shown where it's written instead of only in the problems panel, cut to two lines
+                match expression::check_condition(&condition, variables) {
+                    Ok(_) => self.draw_lable(d, font, "Branches:", Vector2 { x: 10., y: 45. }),
+                    Err(err) => {
+                        let lines =
+                            text_edit::wrap_text(&err, 150, |line| font.measure_text(line, 16));
+                        for (i, (start, end)) in lines.iter().take(2).enumerate() {
+                            let mut line = err[*start..*end].to_string();
+                            if i == 1 && lines.len() > 2 {
+                                line.push_str("...");
+                            }
+                            font.draw_text(
+                                d,
+                                &line,
+                                (self.pos.x + 10.) as i32,
+                                (self.pos.y + 40.) as i32 + i as i32 * 17,
+                                16,
+                                Color::RED,
+                            );
+                        }
+                    }
+                }
+                self.draw_lable(d, font, "If true:", Vector2 { x: 10., y: 80. });
+                self.draw_lable(d, font, "If false:", Vector2 { x: 10., y: 115. });
+                self.draw_lable(d, font, "If not set:", Vector2 { x: 10., y: 150. });
             }
             NodeTypes::SetFlag => {
                 for i in &self.widgets {
                     i.draw(
                         d,
+                        font,
                         self.pos,
                         Some(node_data.flag_to_set.clone().unwrap()),
                         Some(node_data.value_to_set.clone().unwrap()),
@@ -618,7 +956,13 @@ impl Card {
                 }
             }
             NodeTypes::EmitEvent => {
-                self.widgets[0].draw(d, self.pos, Some(node_data.event_to_emit.unwrap()), None);
+                self.widgets[0].draw(
+                    d,
+                    font,
+                    self.pos,
+                    Some(node_data.event_to_emit.unwrap()),
+                    None,
+                );
 
                 for (j, wid_i) in self
                     .widgets
@@ -640,20 +984,110 @@ impl Card {
                     let arg = node_data.event_data.clone().unwrap()[j / 2].clone();
 
                     if is_key {
-                        wid_i.draw(d, self.pos, Some(arg.0), None);
+                        wid_i.draw(d, font, self.pos, Some(arg.0), None);
                     } else {
-                        wid_i.draw(d, self.pos, Some(arg.1), None);
+                        wid_i.draw(d, font, self.pos, Some(arg.1), None);
+                    }
+                }
+
+                self.widgets[self.widgets.len() - 1].draw(d, font, self.pos, None, None)
+            }
+            NodeTypes::Start => {
+                self.draw_lable(d, font, "Start:", Vector2 { x: 10., y: 10. });
+                self.widgets[0].draw(d, font, self.pos, node_data.entry_name, None);
+                self.widgets[1].draw(d, font, self.pos, None, None);
+            }
+            NodeTypes::SetVariable => {
+                let operation = match node_data.operation.unwrap() {
+                    Operation::Assign => "Set to",
+                    Operation::Add => "Add",
+                    Operation::Subtract => "Subtract",
+                };
+                self.widgets[0].draw(d, font, self.pos, node_data.variable, None);
+                self.widgets[1].draw(d, font, self.pos, Some(operation.to_string()), None);
+                self.widgets[2].draw(d, font, self.pos, node_data.variable_value, None);
+                self.widgets[3].draw(d, font, self.pos, None, None);
+            }
+            NodeTypes::Random => {
+                let mode = match node_data.random_mode.unwrap() {
+                    RandomMode::Weighted => "Random",
+                    RandomMode::Shuffle => "Shuffle",
+                    RandomMode::Cycle => "Cycle in order",
+                };
+                self.widgets[0].draw(d, font, self.pos, Some(mode.to_string()), None);
+
+                let weights = node_data.weights.unwrap();
+                let total: i64 = weights.iter().map(|w| (*w).max(0)).sum();
+                for i in &self.widgets[1..] {
+                    match i.editing_node_member {
+                        Some(NodeMember::Weight(output_i)) => {
+                            let weight = weights[output_i];
+                            i.draw(d, font, self.pos, Some(weight.to_string()), None);
+
+                            // How often it comes up, next to the weight
+                            if total > 0 {
+                                let share = format!("{}%", weight.max(0) * 100 / total);
+                                let x_pos = (self.pos.x + i.offset.x) as i32 + 145
+                                    - font.measure_text(&share, 19);
+                                let y_pos = (self.pos.y + i.offset.y) as i32 + 3;
+                                font.draw_text(d, &share, x_pos, y_pos, 19, Color::GRAY);
+                            }
+                        }
+                        _ => i.draw(d, font, self.pos, None, None),
                     }
                 }
+            }
+            NodeTypes::Jump => {
+                self.draw_lable(d, font, "Jump to:", Vector2 { x: 10., y: 10. });
+                let target = node_data.jump_target.unwrap();
+                self.widgets[0].draw(
+                    d,
+                    font,
+                    self.pos,
+                    Some(jump_target_label(nodes, &target)),
+                    None,
+                );
 
-                self.widgets[self.widgets.len() - 1].draw(d, self.pos, None, None)
+                let picker = self.pos + JUMP_PICKER_OFFSET;
+                d.draw_circle_v(picker, 10., Color::SKYBLUE);
+                d.draw_circle_v(picker, 5., Color::WHITE);
+
+                let go_to = self.pos + JUMP_GO_TO_OFFSET;
+                let (x_pos, y_pos) = (go_to.x as i32, go_to.y as i32);
+                let (width, height) = (JUMP_GO_TO_SIZE.x as i32, JUMP_GO_TO_SIZE.y as i32);
+                d.draw_rectangle(x_pos, y_pos, width, height, Color::GRAY);
+                d.draw_rectangle(
+                    x_pos + 1,
+                    y_pos + 1,
+                    width - 2,
+                    height - 2,
+                    Color::LIGHTGRAY,
+                );
+                font.draw_text(d, "Go", x_pos + 9, y_pos + 3, 19, Color::BLACK);
+            }
+            NodeTypes::End => {
+                self.draw_lable(d, font, "End, exit code:", Vector2 { x: 10., y: 10. });
+                self.widgets[0].draw(
+                    d,
+                    font,
+                    self.pos,
+                    Some(node_data.get_member_text(&NodeMember::ExitCode)),
+                    None,
+                );
             }
             _ => unimplemented!("{:?}", self.card_type),
         }
     }
 
-    fn draw_lable(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, text: &str, offset: Vector2) {
-        d.draw_text(
+    fn draw_lable(
+        &self,
+        d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+        font: &EditorFont,
+        text: &str,
+        offset: Vector2,
+    ) {
+        font.draw_text(
+            d,
             text,
             (self.pos + offset).x as i32,
             (self.pos + offset).y as i32,
@@ -662,7 +1096,7 @@ impl Card {
         );
     }
 
-    fn draw_card_bg(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>) {
+    fn draw_card_bg(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, selected: bool) {
         let corner_radius = 10;
 
         let x_pos = self.pos.x as i32;
@@ -670,6 +1104,19 @@ impl Card {
         let x_size = self.size.x as i32;
         let y_size = self.size.y as i32;
 
+        if selected {
+            d.draw_rectangle_lines_ex(
+                Rectangle {
+                    x: (x_pos - 16) as f32,
+                    y: (y_pos - 16) as f32,
+                    width: (x_size + 32) as f32,
+                    height: (y_size + 32) as f32,
+                },
+                4,
+                Color::ORANGE,
+            );
+        }
+
         d.draw_circle(x_pos, y_pos, 12., Color::BROWN);
 
         d.draw_rectangle(x_pos, y_pos - 12, x_size, 24, Color::BROWN);
@@ -716,9 +1163,15 @@ impl Card {
             Color::SKYBLUE,
         );
 
-        d.draw_circle(x_pos, y_pos, corner_radius as f32, Color::PINK);
+        // Conversations only begin at starts, nothing can go into them
+        if self.card_type != NodeTypes::Start {
+            d.draw_circle(x_pos, y_pos, corner_radius as f32, Color::PINK);
+        }
 
-        if self.card_type == NodeTypes::Branches || self.card_type == NodeTypes::EmitEvent {
+        if self.card_type == NodeTypes::Branches
+            || self.card_type == NodeTypes::EmitEvent
+            || self.card_type == NodeTypes::Random
+        {
             d.draw_circle(
                 x_pos + x_size / 2,
                 y_pos + y_size,
@@ -756,12 +1209,37 @@ impl Card {
 enum CanvasContextMenuState {
     Hidden,
     NewCard,
+    CardOptions(String), // id of the card that was right clicked
 }
 
 enum CanvasContextMenuNotification {
     CreateNewCard(NodeTypes),
+    DeleteCard(String),
+    AlignSelected(Alignment),
+}
+
+#[derive(Clone, Copy)]
+enum Alignment {
+    Left,
+    Top,
 }
 
+const CARD_OPTIONS: [&str; 3] = ["Delete", "Align left", "Align top"];
+
+// In the order of the icons in the new card menu, the first five come from its image
+const NEW_CARD_TYPES: [NodeTypes; 10] = [
+    NodeTypes::Dialogue,
+    NodeTypes::Branches,
+    NodeTypes::SetFlag,
+    NodeTypes::Conditional,
+    NodeTypes::EmitEvent,
+    NodeTypes::Start,
+    NodeTypes::End,
+    NodeTypes::SetVariable,
+    NodeTypes::Random,
+    NodeTypes::Jump,
+];
+
 struct CanvasContextMenu {
     state: CanvasContextMenuState,
     pos: Vector2,
@@ -778,50 +1256,41 @@ impl CanvasContextMenu {
             return None;
         }
 
-        match self.state {
+        match &self.state {
             CanvasContextMenuState::Hidden => {}
+            CanvasContextMenuState::CardOptions(id) => {
+                let id = id.clone();
+                self.state = CanvasContextMenuState::Hidden;
+
+                if m_w_pos.x < self.pos.x || m_w_pos.x > self.pos.x + 120. {
+                    return None;
+                }
+                let hovering = ((m_w_pos - self.pos).y / 30.).floor() as i64;
+                match hovering {
+                    0 => return Some(CanvasContextMenuNotification::DeleteCard(id)),
+                    1 => {
+                        return Some(CanvasContextMenuNotification::AlignSelected(
+                            Alignment::Left,
+                        ))
+                    }
+                    2 => return Some(CanvasContextMenuNotification::AlignSelected(Alignment::Top)),
+                    _ => {}
+                }
+            }
             CanvasContextMenuState::NewCard => {
                 let hovering = ((m_w_pos - self.pos).x / 30.).floor() as i64;
-                if hovering > 4
+                self.state = CanvasContextMenuState::Hidden;
+                if hovering >= NEW_CARD_TYPES.len() as i64
                     || hovering < 0
                     || m_w_pos.y < self.pos.y
                     || m_w_pos.y > self.pos.y + 30.
                 {
-                    self.state = CanvasContextMenuState::Hidden;
                     return None;
                 }
 
-                self.state = CanvasContextMenuState::Hidden;
-                match hovering {
-                    0 => {
-                        return Some(CanvasContextMenuNotification::CreateNewCard(
-                            NodeTypes::Dialogue,
-                        ));
-                    }
-                    1 => {
-                        return Some(CanvasContextMenuNotification::CreateNewCard(
-                            NodeTypes::Branches,
-                        ));
-                    }
-                    2 => {
-                        return Some(CanvasContextMenuNotification::CreateNewCard(
-                            NodeTypes::SetFlag,
-                        ));
-                    }
-                    3 => {
-                        return Some(CanvasContextMenuNotification::CreateNewCard(
-                            NodeTypes::Conditional,
-                        ));
-                    }
-                    4 => {
-                        return Some(CanvasContextMenuNotification::CreateNewCard(
-                            NodeTypes::EmitEvent,
-                        ))
-                    }
-                    _ => {
-                        panic!()
-                    }
-                }
+                return Some(CanvasContextMenuNotification::CreateNewCard(
+                    NEW_CARD_TYPES[hovering as usize].clone(),
+                ));
             }
         }
         return None;
@@ -830,8 +1299,49 @@ impl CanvasContextMenu {
     fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, mouse_world_pos: Vector2) {
         match self.state {
             CanvasContextMenuState::Hidden => {}
+            CanvasContextMenuState::CardOptions(_) => {
+                let height = CARD_OPTIONS.len() as i32 * 30;
+                d.draw_rectangle(
+                    self.pos.x as i32,
+                    self.pos.y as i32,
+                    120,
+                    height,
+                    Color::PINK,
+                );
+
+                let hovering = ((mouse_world_pos - self.pos).y / 30.).floor() as i64;
+                if mouse_world_pos.x > self.pos.x
+                    && mouse_world_pos.x < self.pos.x + 120.
+                    && hovering >= 0
+                    && hovering < CARD_OPTIONS.len() as i64
+                {
+                    d.draw_rectangle(
+                        self.pos.x as i32,
+                        self.pos.y as i32 + hovering as i32 * 30,
+                        120,
+                        30,
+                        Color {
+                            r: 0,
+                            g: 0,
+                            b: 0,
+                            a: 50,
+                        },
+                    );
+                }
+
+                for (i, option) in CARD_OPTIONS.iter().enumerate() {
+                    d.draw_text(
+                        option,
+                        self.pos.x as i32 + 8,
+                        self.pos.y as i32 + 5 + i as i32 * 30,
+                        20,
+                        Color::BLACK,
+                    );
+                }
+            }
             CanvasContextMenuState::NewCard => {
-                d.draw_rectangle(self.pos.x as i32, self.pos.y as i32, 150, 30, Color::PINK);
+                let width = NEW_CARD_TYPES.len() as i32 * 30;
+                d.draw_rectangle(self.pos.x as i32, self.pos.y as i32, width, 30, Color::PINK);
                 d.draw_texture(
                     self.images.get("new_card").unwrap(),
                     self.pos.x as i32,
@@ -839,9 +1349,61 @@ impl CanvasContextMenu {
                     Color::WHITE,
                 );
 
+                // Start, end, set variable, random and jump aren't in the image
+                let start_x = self.pos.x + 5. * 30.;
+                d.draw_triangle(
+                    Vector2 {
+                        x: start_x + 9.,
+                        y: self.pos.y + 6.,
+                    },
+                    Vector2 {
+                        x: start_x + 9.,
+                        y: self.pos.y + 24.,
+                    },
+                    Vector2 {
+                        x: start_x + 24.,
+                        y: self.pos.y + 15.,
+                    },
+                    Color::BLACK,
+                );
+                let end_x = self.pos.x as i32 + 6 * 30;
+                d.draw_rectangle(end_x + 7, self.pos.y as i32 + 7, 16, 16, Color::RED);
+                let variable_x = self.pos.x as i32 + 7 * 30;
+                d.draw_text(
+                    "x=",
+                    variable_x + 4,
+                    self.pos.y as i32 + 5,
+                    20,
+                    Color::BLACK,
+                );
+                // A die showing five
+                let random_x = self.pos.x as i32 + 8 * 30;
+                let random_y = self.pos.y as i32;
+                d.draw_rectangle(random_x + 6, random_y + 6, 18, 18, Color::BLACK);
+                d.draw_rectangle(random_x + 8, random_y + 8, 14, 14, Color::WHITE);
+                for (dot_x, dot_y) in [(11, 11), (19, 11), (15, 15), (11, 19), (19, 19)] {
+                    d.draw_circle(random_x + dot_x, random_y + dot_y, 1.5, Color::BLACK);
+                }
+                // A ring like the picker on the card, and an arrow to it
+                let jump_x = self.pos.x as i32 + 9 * 30;
+                d.draw_line_ex(
+                    Vector2 {
+                        x: jump_x as f32 + 4.,
+                        y: self.pos.y + 15.,
+                    },
+                    Vector2 {
+                        x: jump_x as f32 + 14.,
+                        y: self.pos.y + 15.,
+                    },
+                    3.,
+                    Color::BLACK,
+                );
+                d.draw_circle(jump_x + 20, self.pos.y as i32 + 15, 6., Color::SKYBLUE);
+                d.draw_circle(jump_x + 20, self.pos.y as i32 + 15, 3., Color::WHITE);
+
                 let hovering = ((mouse_world_pos - self.pos).x / 30.).floor() as i64;
 
-                if hovering < 5
+                if hovering < NEW_CARD_TYPES.len() as i64
                     && hovering >= 0
                     && mouse_world_pos.y > self.pos.y
                     && mouse_world_pos.y < self.pos.y + 30.
@@ -864,15 +1426,39 @@ impl CanvasContextMenu {
     }
 }
 
+#[derive(Clone)]
 enum CanvasSceneStates {
     Roaming,
     EditingTextInput(String, NodeMember), // Id the currently being modified Node
+    EditingVariable(usize, VariableField), // index in the variables panel
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VariableField {
+    Name,
+    Default,
+}
+
+// Seconds a key has to be held before it starts repeating, and then between each repeat
+const KEY_REPEAT_DELAY: f32 = 0.5;
+const KEY_REPEAT_RATE: f32 = 0.035;
+
+// How many edits can be undone, can be changed with the DIALOGUE_MAKER_UNDO_DEPTH env variable
+const DEFAULT_UNDO_DEPTH: usize = 200;
+
+// Everything an undo brings back
+#[derive(Clone, PartialEq)]
+struct CanvasSnapshot {
+    node_pool: Vec<Node>,
+    variables: Vec<Variable>,
+    cards: Vec<Card>,
 }
 
 struct CanvasScene {
     cam: Camera2D,
     cards: Vec<Card>,
     node_pool: Vec<Node>,
+    variables: Vec<Variable>,
     state: CanvasSceneStates,
 
     // mouse state
@@ -882,236 +1468,670 @@ struct CanvasScene {
     last_r_mouse_pressed: f32,
 
     context_menu: CanvasContextMenu,
+    selected_cards: HashSet<String>,
+    history: History<CanvasSnapshot>,
+
+    font: EditorFont,
+
+    file_path: Option<String>, // where Ctrl+S saves to, None until the project is saved or opened
+    saved: CanvasSnapshot,     // the project as it is in the file, to tell if there are changes
+
+    // text input overlay
+    text_input: TextEdit,
+    text_input_original: String, // put back when the edit is canceled
+    key_repeat: Option<(KeyboardKey, f32)>, // key being held, seconds it has been held for
+
+    notice: Option<(String, f32)>, // message, seconds it has been shown for
+
+    // problems panel
+    problems: Vec<Problem>,
+    problems_panel_open: bool,
+    problems_scroll: usize,
+    problems_age: f32, // seconds since the project was last validated
+
+    variables_panel_open: bool,
+    variables_scroll: usize,
 }
 
+// In screen coordinates, the panel stays in place when the camera moves
+const PROBLEMS_PANEL_X: f32 = 1280. - 430.;
+const PROBLEMS_PANEL_Y: f32 = 10.;
+const PROBLEMS_PANEL_WIDTH: f32 = 420.;
+const PROBLEMS_HEADER_HEIGHT: f32 = 30.;
+const PROBLEMS_ROW_HEIGHT: f32 = 22.;
+const PROBLEMS_MAX_ROWS: usize = 25;
+
+// Also in screen coordinates, the columns are name, type, default and the delete button
+const VARIABLES_PANEL_X: f32 = 10.;
+const VARIABLES_PANEL_Y: f32 = 40.;
+const VARIABLES_PANEL_WIDTH: f32 = 400.;
+const VARIABLES_HEADER_HEIGHT: f32 = 30.;
+const VARIABLES_ROW_HEIGHT: f32 = 26.;
+const VARIABLES_MAX_ROWS: usize = 20;
+const VARIABLES_COLUMNS: [f32; 4] = [6., 172., 238., 374.]; // x of each column in the panel
+
 impl CanvasScene {
-    fn save_to_file(&self) -> bool {
+    // Saves to the file that is open, or asks where to save if there is none or save_as is set
+    fn save_to_file(&mut self, save_as: bool) -> bool {
+        // Problems don't stop the save, they're only pointed out
+        self.run_validation();
+        let errors = self
+            .problems
+            .iter()
+            .filter(|p| p.severity == Severity::Error)
+            .count();
+        if errors > 0 {
+            self.notify_user(format!(
+                "There are {} errors, see the problems panel",
+                errors
+            ));
+        }
+
+        let path = match (&self.file_path, save_as) {
+            (Some(path), false) => path.clone(),
+            _ => match self.ask_save_path() {
+                Some(path) => path,
+                None => return false,
+            },
+        };
+
+        let document = Document {
+            nodes: self.node_pool.clone(),
+            variables: self.variables.clone(),
+            layout: self.get_layout(),
+            ..Document::new()
+        };
+
+        match document.save_to_file(&path) {
+            Ok(()) => println!("SAVE_FILE_INFO: File written successfully"),
+            Err(e) => {
+                println!("SAVE_FILE_ERR: {:?}", e);
+                self.notify_user(format!("Could not save file, {}", e));
+                return false;
+            }
+        }
+
+        self.file_path = Some(path);
+        self.saved = self.snapshot();
+        true
+    }
+
+    #[cfg(feature = "file_dialogs")]
+    fn ask_save_path(&mut self) -> Option<String> {
         let mut dialogue = nfd::dialog_save();
         let dialogue = dialogue.filter("json");
         let res = dialogue.open();
-        let mut path = "".to_string();
         match res {
             Ok(nfd::Response::Okay(file_path)) => {
                 println!("SAVE_FILE_INFO: File selected: {}", file_path);
-                path = file_path;
+                let mut path = file_path;
                 if !path.ends_with(".json") {
                     path.push_str(".json");
                 }
+                return Some(path);
             }
             Ok(nfd::Response::Cancel) => {
                 println!("SAVE_FILE_INFO: User cancelled the dialog");
-                return false;
             }
             Ok(nfd::Response::OkayMultiple(_)) => {
                 println!("SAVE_FILE_ERR: Tried to open multiple files when it shouldn't?");
-                return false;
             }
             Err(error) => {
                 println!("SAVE_FILE_ERR: {}", error);
-                return false;
+                self.notify_user(format!("Could not open the save dialog, {}", error));
             }
         }
+        None
+    }
 
-        let mut obj = JsonObject::new();
-
-        for n in &self.node_pool {
-            obj.push_obj(&n.id);
-            let sub_obj = obj.get_obj_ref(&n.id).unwrap();
-            match n.node_type {
-                NodeTypes::Dialogue => {
-                    // obj.set_string("id", n.id.as_str());
-                    sub_obj.set_string("type", "dialogue");
-                    sub_obj.set_string("character", &n.character.clone().unwrap());
-                    sub_obj.set_string("dialogue", &n.dialogue.clone().unwrap());
-                    sub_obj.set_string("next", &n.front_links[0]);
-                }
-                NodeTypes::Branches => {
-                    sub_obj.set_string("type", "branches");
-                    sub_obj.push_obj("branches");
-                    let exits = sub_obj.get_obj_ref("branches").unwrap();
-                    for (i, o) in n.branches.clone().unwrap().iter().enumerate() {
-                        exits.set_string(o, &n.front_links[i]);
-                    }
-                }
-                NodeTypes::Conditional => {
-                    sub_obj.set_string("type", "conditional");
-                    sub_obj.set_string("flag_to_check", &n.flag_to_check.clone().unwrap());
-                    sub_obj.push_obj("if");
-                    let exits = sub_obj.get_obj_ref("if").unwrap();
-                    exits.set_string("true", &n.front_links[0]);
-                    exits.set_string("false", &n.front_links[1]);
-                    exits.set_string("not_set", &n.front_links[2]);
-                }
-                NodeTypes::SetFlag => {
-                    sub_obj.set_string("type", "set_flag");
-                    sub_obj.set_string("flag_to_set", &n.flag_to_set.clone().unwrap());
-                    sub_obj.set_bool("value", n.value_to_set.clone().unwrap());
-                    sub_obj.set_string("next", &n.front_links[0]);
-                }
-                NodeTypes::EmitEvent => {
-                    sub_obj.set_string("type", "emit_event");
-                    sub_obj.set_string("event", &n.event_to_emit.clone().unwrap());
-                    sub_obj.push_obj("args");
-                    let exits = sub_obj.get_obj_ref("args").unwrap();
-                    for o in n.event_data.clone().unwrap() {
-                        exits.set_string(&o.0, &o.1);
-                    }
-                    sub_obj.set_string("next", &n.front_links[0]);
-                }
-                _ => unimplemented!("{:?}", n.node_type),
-            }
-            // obj.print();
-        }
+    #[cfg(not(feature = "file_dialogs"))]
+    fn ask_save_path(&mut self) -> Option<String> {
+        println!("SAVE_FILE_ERR: Built without file dialogs");
+        self.notify_user("No file dialogs in this build, start the editor with a path to save to");
+        None
+    }
 
-        let file_content = obj.stringify();
-        match std::fs::write(path, file_content) {
-            Ok(()) => println!("SAVE_FILE_INFO: File written successfully"),
-            Err(e) => println!("SAVE_FILE_ERR: {:?}", e),
+    fn load_from_file(&mut self) -> bool {
+        match self.ask_open_path() {
+            Some(path) => self.open_file(path),
+            None => false,
         }
-
-        true
     }
 
-    fn load_from_file(&mut self) -> bool {
+    #[cfg(feature = "file_dialogs")]
+    fn ask_open_path(&mut self) -> Option<String> {
         let res = nfd::open_file_dialog(Some("json"), None);
-        let mut path = "".to_string();
         match res {
             Ok(nfd::Response::Okay(file_path)) => {
                 println!("LOAD_FILE_INFO: File selected: {}", file_path);
-                path = file_path;
+                return Some(file_path);
             }
             Ok(nfd::Response::Cancel) => {
                 println!("LOAD_FILE_INFO: User cancelled the dialog");
-                return false;
             }
             Ok(nfd::Response::OkayMultiple(_)) => {
                 println!("LOAD_FILE_INFO: Tried to open multiple files when it shouldn't?");
-                return false;
             }
             Err(error) => {
                 println!("LOAD_FILE_ERR: {}", error);
-                return false;
+                self.notify_user(format!("Could not open the file dialog, {}", error));
             }
         }
+        None
+    }
 
-        let file_res = std::fs::read(path);
-        let file_content;
-        match file_res {
-            Ok(res) => {
-                file_content = String::from_utf8(res).unwrap();
-            }
+    #[cfg(not(feature = "file_dialogs"))]
+    fn ask_open_path(&mut self) -> Option<String> {
+        println!("LOAD_FILE_ERR: Built without file dialogs");
+        self.notify_user("No file dialogs in this build, start the editor with the path to open");
+        None
+    }
+
+    fn open_file(&mut self, path: String) -> bool {
+        let document = match Document::load_from_file(&path) {
+            Ok(found) => found,
             Err(err) => {
                 println!("LOAD_FILE_ERR: {}", err);
+                self.notify_user(format!("Could not load file, {}", err));
                 return false;
             }
+        };
+
+        self.node_pool = document.nodes;
+        self.variables = document.variables;
+        self.cards.clear();
+        self.selected_cards.clear();
+
+        self.parse_node_pool();
+        self.apply_layout(&document.layout);
+        for id in self
+            .node_pool
+            .iter()
+            .map(|n| n.id.clone())
+            .collect::<Vec<String>>()
+        {
+            self.fit_card_to_content(&id);
+        }
+        self.history.clear();
+        self.file_path = Some(path);
+        self.saved = self.snapshot();
+
+        if let Some(version) = document.upgraded_from {
+            println!("LOAD_FILE_INFO: Upgraded from format version {}", version);
+            self.notify_user(format!(
+                "This file was made with an older version (format {}), it will be saved in the new format",
+                version
+            ));
         }
 
-        let mut parser = Parser::new();
-        parser.load(file_content);
-        let parsed_obj = parser.parse();
+        true
+    }
 
-        self.node_pool.clear();
-        self.cards.clear();
+    fn has_unsaved_changes(&self) -> bool {
+        self.node_pool != self.saved.node_pool
+            || self.variables != self.saved.variables
+            || self.cards != self.saved.cards
+    }
 
-        for (n_id, n_obj) in parsed_obj.children {
-            match n_obj {
-                JsonType::Object(obj) => match obj.get_string("type") {
-                    Ok(n_type) => match n_type.as_str() {
-                        "dialogue" => self.node_pool.push(Node::new_dialogue(
-                            n_id,
-                            obj.get_string("character").unwrap(),
-                            obj.get_string("dialogue").unwrap(),
-                            vec![obj.get_string("next").unwrap()],
-                        )),
-                        "branches" => {
-                            let mut branches_vec: Vec<String> = vec![];
-                            let mut front_vec: Vec<String> = vec![];
-
-                            for exit in obj.get_obj("branches").unwrap().children {
-                                branches_vec.push(exit.0);
-
-                                match exit.1 {
-                                    JsonType::String(next_node) => front_vec.push(next_node),
-                                    _ => {
-                                        println!("LOAD_FILE_ERR: branches' exits must be Strings.");
-                                        return false;
-                                    }
-                                }
-                            }
+    // File name and a * when there are unsaved changes, like most editors
+    fn window_title(&self) -> String {
+        let name = match &self.file_path {
+            Some(path) => match std::path::Path::new(path).file_name() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => path.clone(),
+            },
+            None => "Untitled".to_string(),
+        };
+        let dirty = if self.has_unsaved_changes() { "*" } else { "" };
+        format!("{}{} - Dialogue maker", name, dirty)
+    }
 
-                            self.node_pool
-                                .push(Node::new_branches(n_id, branches_vec, front_vec))
-                        }
-                        "conditional" => {
-                            let exits = obj.get_obj("if").unwrap();
-                            self.node_pool.push(Node::new_conditional(
-                                n_id,
-                                obj.get_string("flag_to_check").unwrap(),
-                                vec![
-                                    exits.get_string("true").unwrap(),
-                                    exits.get_string("false").unwrap(),
-                                    exits.get_string("not_set").unwrap(),
-                                ],
-                            ))
-                        }
-                        "set_flag" => self.node_pool.push(Node::new_set_flag(
-                            n_id,
-                            obj.get_string("flag_to_set").unwrap(),
-                            obj.get_bool("value").unwrap(),
-                            vec![obj.get_string("next").unwrap()],
-                        )),
-                        "emit_event" => {
-                            let mut arg_vec = vec![];
-
-                            for arg in obj.get_obj("args").unwrap().children {
-                                let val = match arg.1 {
-                                    JsonType::String(found_val) => found_val,
-                                    _ => {
-                                        println!("LOAD_FILE_ERR: Options' exits must be Strings.");
-                                        return false;
-                                    }
-                                };
-
-                                arg_vec.push((arg.0, val));
-                            }
+    fn get_layout(&self) -> EditorLayout {
+        let mut to_return = EditorLayout {
+            camera: Some(CameraLayout {
+                x: self.cam.target.x,
+                y: self.cam.target.y,
+                zoom: self.cam.zoom,
+            }),
+            cards: HashMap::new(),
+        };
 
-                            self.node_pool.push(Node::new_emit_event(
-                                n_id,
-                                obj.get_string("event").unwrap(),
-                                arg_vec,
-                                vec![obj.get_string("next").unwrap()],
-                            ))
-                        }
-                        _ => unimplemented!("{}", n_type),
-                    },
-                    Err(err) => {
-                        println!("LOAD_FILE_ERR: {:?}", err);
-                        return false;
-                    }
+        for c in &self.cards {
+            to_return.cards.insert(
+                c.node_ref.clone(),
+                CardLayout {
+                    x: c.pos.x,
+                    y: c.pos.y,
+                    width: c.size.x,
+                    height: c.size.y,
                 },
-                _ => return false,
+            );
+        }
+
+        to_return
+    }
+
+    // Cards without saved data just keep the position from parse_node_pool
+    fn apply_layout(&mut self, layout: &EditorLayout) {
+        match &layout.camera {
+            Some(cam) => {
+                self.cam.target = Vector2 { x: cam.x, y: cam.y };
+                self.cam.zoom = cam.zoom;
             }
+            None => println!("LOAD_FILE_INFO: No camera data found"),
         }
 
-        self.parse_node_pool();
+        for c in &mut self.cards {
+            match layout.cards.get(&c.node_ref) {
+                Some(card) => {
+                    c.pos = Vector2 {
+                        x: card.x,
+                        y: card.y,
+                    };
+                    c.size = Vector2 {
+                        x: card.width,
+                        y: card.height,
+                    };
+                }
+                None => println!("LOAD_FILE_INFO: No layout data for card {}", c.node_ref),
+            }
+        }
+    }
+
+    fn reload_font_if_needed(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) {
+        if self.font.reload_if_needed(rl, thread) {
+            for id in self
+                .node_pool
+                .iter()
+                .map(|n| n.id.clone())
+                .collect::<Vec<String>>()
+            {
+                self.fit_card_to_content(&id);
+            }
+        }
+    }
+
+    fn fit_card_to_content(&mut self, id: &String) {
+        let node_data = self.copy_node_data(id);
+        if let Some(card) = self.cards.iter_mut().find(|c| &c.node_ref == id) {
+            card.fit_to_content(&node_data, &self.font);
+        }
+    }
+
+    fn run_validation(&mut self) {
+        self.problems = validate(&self.node_pool, &self.variables);
+        self.problems_panel_open = !self.problems.is_empty();
+        self.problems_scroll = self
+            .problems_scroll
+            .min(self.problems.len().saturating_sub(1));
+        self.problems_age = 0.;
+    }
+
+    fn jump_to_card(&mut self, id: &String) {
+        match self.cards.iter().find(|c| &c.node_ref == id) {
+            Some(card) => {
+                self.cam.target = card.pos + card.size.scale_by(0.5);
+                self.selected_cards.clear();
+                self.selected_cards.insert(id.clone());
+            }
+            None => self.notify_user(format!("Node {} doesn't exist anymore", id)),
+        }
+    }
+
+    // Returns true when the mouse is over the panel, so the canvas doesn't also react to it
+    fn update_problems_panel(&mut self, rl: &RaylibHandle) -> bool {
+        if !self.problems_panel_open {
+            return false;
+        }
+
+        // Kept up to date while open, but not every frame since big projects take a while
+        self.problems_age += rl.get_frame_time();
+        if self.problems_age > 0.5 {
+            self.run_validation();
+            if !self.problems_panel_open {
+                return false;
+            }
+        }
+
+        match self.mouse_sate {
+            CanvasMouseState::Roaming => {}
+            _ => return false,
+        }
+        match self.state {
+            CanvasSceneStates::Roaming => {}
+            _ => return false,
+        }
+
+        let m_pos = rl.get_mouse_position();
+        let rows = self.problems.len().min(PROBLEMS_MAX_ROWS);
+        let height = PROBLEMS_HEADER_HEIGHT + rows as f32 * PROBLEMS_ROW_HEIGHT;
+        if m_pos.x < PROBLEMS_PANEL_X
+            || m_pos.x > PROBLEMS_PANEL_X + PROBLEMS_PANEL_WIDTH
+            || m_pos.y < PROBLEMS_PANEL_Y
+            || m_pos.y > PROBLEMS_PANEL_Y + height
+        {
+            return false;
+        }
+
+        let wheel = rl.get_mouse_wheel_move();
+        if wheel < 0. && self.problems_scroll + PROBLEMS_MAX_ROWS < self.problems.len() {
+            self.problems_scroll += 1;
+        }
+        if wheel > 0. && self.problems_scroll > 0 {
+            self.problems_scroll -= 1;
+        }
+
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+            let y = m_pos.y - PROBLEMS_PANEL_Y - PROBLEMS_HEADER_HEIGHT;
+            if y < 0. {
+                // The header closes the panel
+                self.problems_panel_open = false;
+            } else {
+                let i = (y / PROBLEMS_ROW_HEIGHT) as usize + self.problems_scroll;
+                if let Some(problem) = self.problems.get(i) {
+                    let id = problem.node_id.clone();
+                    self.jump_to_card(&id);
+                }
+            }
+        }
 
         true
     }
 
-    fn get_free_node_id(&self) -> String {
-        'outer_loop: for i in 1..99999 {
-            let cur_i = format!("{:0>5}", i.to_string()); // i with left 0 tabs
+    pub fn draw_problems_panel(&self, d: &mut RaylibDrawHandle) {
+        if !self.problems_panel_open {
+            return;
+        }
+
+        let x_pos = PROBLEMS_PANEL_X as i32;
+        let y_pos = PROBLEMS_PANEL_Y as i32;
+        let width = PROBLEMS_PANEL_WIDTH as i32;
+        let row_height = PROBLEMS_ROW_HEIGHT as i32;
+        let rows = self.problems.len().min(PROBLEMS_MAX_ROWS);
+        let height = PROBLEMS_HEADER_HEIGHT as i32 + rows as i32 * row_height;
+
+        d.draw_rectangle(x_pos, y_pos, width, height, Color::LIGHTGRAY);
+        d.draw_rectangle(
+            x_pos,
+            y_pos,
+            width,
+            PROBLEMS_HEADER_HEIGHT as i32,
+            Color::BROWN,
+        );
+
+        let errors = self
+            .problems
+            .iter()
+            .filter(|p| p.severity == Severity::Error)
+            .count();
+        let header = format!(
+            "{} errors, {} warnings (click to close)",
+            errors,
+            self.problems.len() - errors
+        );
+        self.font
+            .draw_text(d, &header, x_pos + 8, y_pos + 6, 18, Color::WHITE);
+
+        let m_pos = d.get_mouse_position();
+        for (row, problem) in self
+            .problems
+            .iter()
+            .skip(self.problems_scroll)
+            .take(PROBLEMS_MAX_ROWS)
+            .enumerate()
+        {
+            let row_y = y_pos + PROBLEMS_HEADER_HEIGHT as i32 + row as i32 * row_height;
+
+            let hovered = m_pos.x > PROBLEMS_PANEL_X
+                && m_pos.x < PROBLEMS_PANEL_X + PROBLEMS_PANEL_WIDTH
+                && m_pos.y > row_y as f32
+                && m_pos.y < (row_y + row_height) as f32;
+            if hovered {
+                d.draw_rectangle(x_pos, row_y, width, row_height, Color::GRAY);
+            }
 
-            for j in &self.node_pool {
-                if j.id == cur_i {
-                    continue 'outer_loop;
+            let color = match problem.severity {
+                Severity::Error => Color::RED,
+                Severity::Warning => Color::ORANGE,
+            };
+            d.draw_rectangle(x_pos + 6, row_y + 6, 10, 10, color);
+
+            // Cut to fit the panel
+            let mut text = format!("{}  {}", problem.node_id, problem.message);
+            if self.font.measure_text(&text, 16) > width - 30 {
+                while self.font.measure_text(&text, 16) > width - 45 {
+                    text.pop();
                 }
+                text.push_str("...");
             }
+            self.font
+                .draw_text(d, &text, x_pos + 22, row_y + 3, 16, Color::BLACK);
+        }
+    }
+
+    // Returns true when the mouse is over the panel, like the problems panel
+    fn update_variables_panel(&mut self, rl: &RaylibHandle) -> bool {
+        if !self.variables_panel_open {
+            return false;
+        }
+        match self.mouse_sate {
+            CanvasMouseState::Roaming => {}
+            _ => return false,
+        }
+        match self.state {
+            CanvasSceneStates::Roaming => {}
+            _ => return false,
+        }
+
+        // One more row than there are variables, for the add button
+        let row_count = self.variables.len() + 1;
+        self.variables_scroll = self
+            .variables_scroll
+            .min(row_count.saturating_sub(VARIABLES_MAX_ROWS));
+
+        let m_pos = rl.get_mouse_position();
+        let rows = row_count.min(VARIABLES_MAX_ROWS);
+        let height = VARIABLES_HEADER_HEIGHT + rows as f32 * VARIABLES_ROW_HEIGHT;
+        if m_pos.x < VARIABLES_PANEL_X
+            || m_pos.x > VARIABLES_PANEL_X + VARIABLES_PANEL_WIDTH
+            || m_pos.y < VARIABLES_PANEL_Y
+            || m_pos.y > VARIABLES_PANEL_Y + height
+        {
+            return false;
+        }
+
+        let wheel = rl.get_mouse_wheel_move();
+        if wheel < 0. && self.variables_scroll + VARIABLES_MAX_ROWS < row_count {
+            self.variables_scroll += 1;
+        }
+        if wheel > 0. && self.variables_scroll > 0 {
+            self.variables_scroll -= 1;
+        }
+
+        if !rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+            return true;
+        }
+
+        let y = m_pos.y - VARIABLES_PANEL_Y - VARIABLES_HEADER_HEIGHT;
+        if y < 0. {
+            // The header closes the panel
+            self.variables_panel_open = false;
+            return true;
+        }
+        let i = (y / VARIABLES_ROW_HEIGHT) as usize + self.variables_scroll;
+        let x = m_pos.x - VARIABLES_PANEL_X;
+
+        if i == self.variables.len() {
+            let mut name_i = self.variables.len() + 1;
+            while self
+                .variables
+                .iter()
+                .any(|v| v.name == format!("variable_{}", name_i))
+            {
+                name_i += 1;
+            }
+
+            self.history.record(self.snapshot());
+            self.variables.push(Variable {
+                name: format!("variable_{}", name_i),
+                default: Value::Int(0),
+            });
+            self.edit_variable(i, VariableField::Name);
+        } else if i < self.variables.len() {
+            if x < VARIABLES_COLUMNS[1] {
+                self.edit_variable(i, VariableField::Name);
+            } else if x < VARIABLES_COLUMNS[2] {
+                // Keeps the default when it still makes sense for the new type
+                self.history.record(self.snapshot());
+                let variable = &mut self.variables[i];
+                let var_type = variable.default.var_type().next();
+                variable.default = Value::parse(&variable.default.to_string(), var_type)
+                    .unwrap_or_else(|_| Value::zero(var_type));
+            } else if x < VARIABLES_COLUMNS[3] {
+                self.edit_variable(i, VariableField::Default);
+            } else {
+                self.history.record(self.snapshot());
+                self.variables.remove(i);
+            }
+        }
+
+        true
+    }
+
+    fn edit_variable(&mut self, i: usize, field: VariableField) {
+        let text = match field {
+            VariableField::Name => self.variables[i].name.clone(),
+            VariableField::Default => self.variables[i].default.to_string(),
+        };
+        self.text_input = TextEdit::new(&text);
+        self.text_input_original = text;
+        self.key_repeat = None;
+
+        self.state = CanvasSceneStates::EditingVariable(i, field);
+        self.history.begin_edit(self.snapshot());
+    }
+
+    pub fn draw_variables_panel(&self, d: &mut RaylibDrawHandle) {
+        if !self.variables_panel_open {
+            return;
+        }
+        // Would be drawn over the text being edited
+        match self.state {
+            CanvasSceneStates::Roaming => {}
+            _ => return,
+        }
+
+        let x_pos = VARIABLES_PANEL_X as i32;
+        let y_pos = VARIABLES_PANEL_Y as i32;
+        let width = VARIABLES_PANEL_WIDTH as i32;
+        let row_height = VARIABLES_ROW_HEIGHT as i32;
+        let rows = (self.variables.len() + 1).min(VARIABLES_MAX_ROWS);
+        let height = VARIABLES_HEADER_HEIGHT as i32 + rows as i32 * row_height;
+
+        d.draw_rectangle(x_pos, y_pos, width, height, Color::LIGHTGRAY);
+        d.draw_rectangle(
+            x_pos,
+            y_pos,
+            width,
+            VARIABLES_HEADER_HEIGHT as i32,
+            Color::DARKGREEN,
+        );
+        let header = format!("{} variables (click to close)", self.variables.len());
+        self.font
+            .draw_text(d, &header, x_pos + 8, y_pos + 6, 18, Color::WHITE);
+
+        let m_pos = d.get_mouse_position();
+        for row in 0..rows {
+            let i = row + self.variables_scroll;
+            let row_y = y_pos + VARIABLES_HEADER_HEIGHT as i32 + row as i32 * row_height;
+
+            let hovered = m_pos.x > VARIABLES_PANEL_X
+                && m_pos.x < VARIABLES_PANEL_X + VARIABLES_PANEL_WIDTH
+                && m_pos.y > row_y as f32
+                && m_pos.y < (row_y + row_height) as f32;
+            if hovered {
+                d.draw_rectangle(x_pos, row_y, width, row_height, Color::GRAY);
+            }
+
+            let variable = match self.variables.get(i) {
+                Some(variable) => variable,
+                None => {
+                    self.font.draw_text(
+                        d,
+                        "+ Add variable",
+                        x_pos + VARIABLES_COLUMNS[0] as i32,
+                        row_y + 4,
+                        16,
+                        Color::DARKGREEN,
+                    );
+                    continue;
+                }
+            };
+
+            let default = match &variable.default {
+                Value::String(text) => format!("\"{}\"", text),
+                other => other.to_string(),
+            };
+            let columns = [
+                variable.name.clone(),
+                variable.default.var_type().name().to_string(),
+                default,
+                "x".to_string(),
+            ];
+            for (column, text) in columns.iter().enumerate() {
+                let column_x = VARIABLES_COLUMNS[column] as i32;
+                let column_width = match VARIABLES_COLUMNS.get(column + 1) {
+                    Some(next_x) => *next_x as i32 - column_x,
+                    None => width - column_x,
+                };
+
+                // Cut to fit the column
+                let mut text = text.clone();
+                if self.font.measure_text(&text, 16) > column_width - 6 {
+                    while !text.is_empty() && self.font.measure_text(&text, 16) > column_width - 20
+                    {
+                        text.pop();
+                    }
+                    text.push_str("...");
+                }
+                let color = if column == 3 {
+                    Color::RED
+                } else {
+                    Color::BLACK
+                };
+                self.font
+                    .draw_text(d, &text, x_pos + column_x, row_y + 4, 16, color);
+            }
+        }
+    }
+
+    fn notify_user<T: ToString>(&mut self, message: T) {
+        let message = message.to_string();
+        self.font.add_chars(&message);
+        self.notice = Some((message, 0.));
+    }
 
-            return cur_i;
+    pub fn draw_notice(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>, tlp: Vector2) {
+        if let Some((message, _)) = &self.notice {
+            let font_size = (20. / self.cam.zoom) as i32;
+            let x_pos = (tlp.x + 10. / self.cam.zoom) as i32;
+            let y_pos = (tlp.y + 30. / self.cam.zoom) as i32;
+            let width = self.font.measure_text(message, font_size) + font_size;
+
+            d.draw_rectangle(x_pos, y_pos, width, font_size * 2, Color::LIGHTGRAY);
+            self.font.draw_text(
+                d,
+                message,
+                x_pos + font_size / 2,
+                y_pos + font_size / 2,
+                font_size,
+                Color::MAROON,
+            );
         }
+    }
 
-        panic!("There isn't enough ids.");
+    fn get_free_node_id(&self) -> String {
+        document::get_free_node_id(&self.node_pool)
     }
 
     fn get_node_ref<'a>(&'a mut self, id: &String) -> &'a mut Node {
@@ -1124,6 +2144,249 @@ impl CanvasScene {
         unreachable!()
     }
 
+    // Cards drawn last are on top, so they get picked first
+    fn get_card_at(&self, pos: Vector2) -> Option<String> {
+        for c in self.cards.iter().rev() {
+            if c.contains(pos) {
+                return Some(c.node_ref.clone());
+            }
+        }
+
+        None
+    }
+
+    // Returns the id of the node the connection starts from and the index of its front link
+    fn get_connection_at(&self, pos: Vector2) -> Option<(String, usize)> {
+        let max_distance = 8. / self.cam.zoom;
+
+        for i in &self.node_pool {
+            let i_card = match self.copy_card_data(&i.id) {
+                Some(card) => card,
+                None => continue,
+            };
+            let outputs = i_card.copy_output_widgets();
+
+            for (j, (link, output)) in i.front_links.iter().zip(&outputs).enumerate() {
+                if link.is_empty() {
+                    continue;
+                }
+
+                let start_pos = i_card.pos + output.offset;
+                let end_pos = match self.copy_card_data(link) {
+                    Some(card) => card.pos,
+                    None => continue,
+                };
+                if distance_to_segment(pos, start_pos, end_pos) < max_distance {
+                    return Some((i.id.clone(), j));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn snapshot(&self) -> CanvasSnapshot {
+        CanvasSnapshot {
+            node_pool: self.node_pool.clone(),
+            variables: self.variables.clone(),
+            cards: self.cards.clone(),
+        }
+    }
+
+    fn restore_snapshot(&mut self, snapshot: CanvasSnapshot) {
+        self.node_pool = snapshot.node_pool;
+        self.variables = snapshot.variables;
+        self.cards = snapshot.cards;
+
+        let cards = &self.cards;
+        self.selected_cards
+            .retain(|id| cards.iter().any(|c| &c.node_ref == id));
+        self.context_menu.state = CanvasContextMenuState::Hidden;
+    }
+
+    fn undo(&mut self) {
+        match self.history.undo(self.snapshot()) {
+            Some(snapshot) => self.restore_snapshot(snapshot),
+            None => println!("HISTORY_INFO: Nothing to undo"),
+        }
+    }
+
+    fn redo(&mut self) {
+        match self.history.redo(self.snapshot()) {
+            Some(snapshot) => self.restore_snapshot(snapshot),
+            None => println!("HISTORY_INFO: Nothing to redo"),
+        }
+    }
+
+    fn delete_cards(&mut self, ids: Vec<String>) {
+        if ids.is_empty() {
+            return;
+        }
+
+        self.history.record(self.snapshot());
+        for id in &ids {
+            document::remove_node(&mut self.node_pool, id);
+            self.cards.retain(|c| &c.node_ref != id);
+            self.selected_cards.remove(id);
+        }
+        self.mouse_sate = CanvasMouseState::Roaming;
+    }
+
+    fn copy_selection(&self) -> Document {
+        let mut to_return = Document::new();
+        to_return.nodes = document::copy_nodes(&self.node_pool, &self.selected_cards);
+        to_return.variables = document::used_variables(&self.variables, &to_return.nodes);
+        to_return.layout.cards = self
+            .get_layout()
+            .cards
+            .into_iter()
+            .filter(|(id, _)| self.selected_cards.contains(id))
+            .collect();
+
+        to_return
+    }
+
+    // Uses the same format as saved files, so cards can go from one project to another
+    fn copy_to_clipboard(&mut self) -> bool {
+        if self.selected_cards.is_empty() {
+            return false;
+        }
+
+        set_clipboard_text(&self.copy_selection().to_json().stringify());
+        true
+    }
+
+    fn paste_from_clipboard(&mut self, rl: &RaylibHandle) {
+        let text = match rl.get_clipboard_text() {
+            Ok(text) => text,
+            Err(err) => {
+                println!("CLIPBOARD_ERR: {}", err);
+                return;
+            }
+        };
+
+        match Document::load_from_str(&text) {
+            Ok(pasted) if !pasted.nodes.is_empty() => {
+                let m_pos = self.get_mouse_world_pos(rl);
+                self.paste(pasted, Some(m_pos));
+            }
+            Ok(_) => self.notify_user("Nothing to paste, the clipboard has no cards"),
+            Err(err) => {
+                println!("CLIPBOARD_ERR: {}", err);
+                self.notify_user("Nothing to paste, the clipboard has no cards");
+            }
+        }
+    }
+
+    // With a position the top left pasted card goes there, otherwise they are put a bit to the
+    // bottom right of where they were copied from. Pasted cards end up as the selection.
+    fn paste(&mut self, pasted: Document, at: Option<Vector2>) {
+        self.history.record(self.snapshot());
+
+        let layout = pasted.layout.clone();
+        let first_pasted = self.node_pool.len();
+
+        // Where the cards go relative to each other, nodes without layout are put side by side
+        let mut positions = vec![];
+        let mut x_offset = 0.;
+        for n in &pasted.nodes {
+            let pos = match layout.cards.get(&n.id) {
+                Some(card) => Vector2 {
+                    x: card.x,
+                    y: card.y,
+                },
+                None => {
+                    x_offset += 200.;
+                    Vector2 { x: x_offset, y: 0. }
+                }
+            };
+            positions.push(pos);
+        }
+
+        for v in &pasted.variables {
+            self.font.add_chars(&v.name);
+        }
+        document::add_missing_variables(&mut self.variables, pasted.variables);
+        document::paste_nodes(&mut self.node_pool, pasted.nodes);
+
+        let offset = match at {
+            Some(at) => {
+                let top_left = positions.iter().fold(
+                    Vector2 {
+                        x: f32::MAX,
+                        y: f32::MAX,
+                    },
+                    |acc, pos| Vector2 {
+                        x: acc.x.min(pos.x),
+                        y: acc.y.min(pos.y),
+                    },
+                );
+                at - top_left
+            }
+            None => Vector2 { x: 30., y: 30. },
+        };
+
+        self.selected_cards.clear();
+        for (i, pos) in positions.into_iter().enumerate() {
+            let node = self.node_pool[first_pasted + i].clone();
+            self.font.add_node_chars(&node);
+            self.cards.push(Card::from_node(&node, pos + offset));
+            self.fit_card_to_content(&node.id);
+            self.selected_cards.insert(node.id);
+        }
+    }
+
+    // Lines up the selected cards with the one furthest to the left or to the top
+    fn align_selected(&mut self, alignment: Alignment) {
+        if self.selected_cards.len() < 2 {
+            return;
+        }
+
+        self.history.record(self.snapshot());
+        let selected: Vec<&mut Card> = self
+            .cards
+            .iter_mut()
+            .filter(|c| self.selected_cards.contains(&c.node_ref))
+            .collect();
+
+        match alignment {
+            Alignment::Left => {
+                let x = selected.iter().map(|c| c.pos.x).fold(f32::MAX, f32::min);
+                for c in selected {
+                    c.pos.x = x;
+                }
+            }
+            Alignment::Top => {
+                let y = selected.iter().map(|c| c.pos.y).fold(f32::MAX, f32::min);
+                for c in selected {
+                    c.pos.y = y;
+                }
+            }
+        }
+    }
+
+    // Plain clicks select only the clicked card, shift or ctrl clicks add and remove cards
+    fn update_selection(&mut self, m_pos: Vector2, toggle: bool) {
+        match self.get_card_at(m_pos) {
+            Some(id) => {
+                if toggle {
+                    if !self.selected_cards.remove(&id) {
+                        self.selected_cards.insert(id);
+                    }
+                } else if !self.selected_cards.contains(&id) {
+                    self.selected_cards.clear();
+                    self.selected_cards.insert(id);
+                }
+            }
+            None => {
+                if !toggle {
+                    self.selected_cards.clear();
+                }
+                self.mouse_sate = CanvasMouseState::BoxSelecting(m_pos);
+            }
+        }
+    }
+
     fn get_card_i(&self, id: String) -> usize {
         for (i, c) in self.cards.iter().enumerate() {
             if c.node_ref == id {
@@ -1142,21 +2405,88 @@ impl CanvasScene {
         self.last_l_mouse_pressed += rl.get_frame_time();
         self.last_r_mouse_pressed += rl.get_frame_time();
 
+        if self.update_problems_panel(rl) || self.update_variables_panel(rl) {
+            *last_mouse_pos = rl.get_mouse_position();
+            return;
+        }
+
+        if let Some((_, shown_for)) = &mut self.notice {
+            *shown_for += rl.get_frame_time();
+            if *shown_for > 6. {
+                self.notice = None;
+            }
+        }
+
         match &self.state {
             CanvasSceneStates::Roaming => {
                 self.update_roaming(rl, last_mouse_pos);
             }
             CanvasSceneStates::EditingTextInput(_, _) => {}
-            _ => unimplemented!(),
+            CanvasSceneStates::EditingVariable(_, _) => {}
         }
     }
 
     pub fn update_roaming(&mut self, rl: &RaylibHandle, last_mouse_pos: &mut Vector2) {
-        if rl.is_key_pressed(KeyboardKey::KEY_S) {
-            let test = self.save_to_file();
+        let ctrl_down = rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
+            || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+        let shift_down = rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
+            || rl.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+
+        // Only while nothing is being dragged, so an edit is never undone halfway through
+        if let CanvasMouseState::Roaming = self.mouse_sate {
+            if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_Z) {
+                if shift_down {
+                    self.redo();
+                } else {
+                    self.undo();
+                }
+                return;
+            }
+
+            if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_C) {
+                self.copy_to_clipboard();
+                return;
+            }
+            if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_X) {
+                if self.copy_to_clipboard() {
+                    self.delete_cards(self.selected_cards.iter().cloned().collect());
+                }
+                return;
+            }
+            if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_V) {
+                self.paste_from_clipboard(rl);
+                return;
+            }
+            if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_D) {
+                if !self.selected_cards.is_empty() {
+                    self.paste(self.copy_selection(), None);
+                }
+                return;
+            }
+
+            if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_S) {
+                self.save_to_file(shift_down);
+                return;
+            }
+            if ctrl_down && rl.is_key_pressed(KeyboardKey::KEY_O) {
+                self.load_from_file();
+                return;
+            }
         }
-        if rl.is_key_pressed(KeyboardKey::KEY_L) {
-            let test = self.load_from_file();
+
+        if rl.is_key_pressed(KeyboardKey::KEY_V) && !ctrl_down {
+            self.variables_panel_open = !self.variables_panel_open;
+        }
+
+        if rl.is_key_pressed(KeyboardKey::KEY_P) {
+            if self.problems_panel_open {
+                self.problems_panel_open = false;
+            } else {
+                self.run_validation();
+                if self.problems.is_empty() {
+                    self.notify_user("No problems found");
+                }
+            }
         }
 
         let context_menu_notification = self.context_menu.update(rl, self.get_mouse_world_pos(rl));
@@ -1164,6 +2494,7 @@ impl CanvasScene {
             None => {}
             Some(notification) => match notification {
                 CanvasContextMenuNotification::CreateNewCard(node_type) => {
+                    self.history.record(self.snapshot());
                     let new_id = self.get_free_node_id();
                     match node_type {
                         NodeTypes::Dialogue => {
@@ -1214,17 +2545,77 @@ impl CanvasScene {
                             );
                             self.node_pool.push(new_node);
 
-                            let new_card =
-                                Card::new_emit_event(new_id, vec![], self.get_mouse_world_pos(rl));
+                            let new_card =
+                                Card::new_emit_event(new_id, vec![], self.get_mouse_world_pos(rl));
+                            self.cards.push(new_card);
+                        }
+                        NodeTypes::Start => {
+                            let mut new_node = Node::default_start();
+                            new_node.id = new_id.clone();
+                            self.node_pool.push(new_node);
+
+                            let new_card = Card::new_start(new_id, self.get_mouse_world_pos(rl));
+                            self.cards.push(new_card);
+                        }
+                        NodeTypes::SetVariable => {
+                            let mut new_node = Node::default_set_variable();
+                            new_node.id = new_id.clone();
+                            self.node_pool.push(new_node);
+
+                            let new_card =
+                                Card::new_set_variable(new_id, self.get_mouse_world_pos(rl));
+                            self.cards.push(new_card);
+                        }
+                        NodeTypes::Random => {
+                            let mut new_node = Node::default_random();
+                            new_node.id = new_id.clone();
+                            new_node.push_random_output(1);
+                            new_node.push_random_output(1);
+                            self.node_pool.push(new_node);
+
+                            let new_card =
+                                Card::new_random(new_id, 2, self.get_mouse_world_pos(rl));
+                            self.cards.push(new_card);
+                        }
+                        NodeTypes::Jump => {
+                            let mut new_node = Node::default_jump();
+                            new_node.id = new_id.clone();
+                            self.node_pool.push(new_node);
+
+                            let new_card = Card::new_jump(new_id, self.get_mouse_world_pos(rl));
+                            self.cards.push(new_card);
+                        }
+                        NodeTypes::End => {
+                            let mut new_node = Node::default_end();
+                            new_node.id = new_id.clone();
+                            self.node_pool.push(new_node);
+
+                            let new_card = Card::new_end(new_id, self.get_mouse_world_pos(rl));
                             self.cards.push(new_card);
                         }
 
                         _ => unimplemented!("{:?}", node_type),
                     }
                 }
+                CanvasContextMenuNotification::DeleteCard(id) => {
+                    if self.selected_cards.contains(&id) {
+                        self.delete_cards(self.selected_cards.iter().cloned().collect());
+                    } else {
+                        self.delete_cards(vec![id]);
+                    }
+                    return;
+                }
+                CanvasContextMenuNotification::AlignSelected(alignment) => {
+                    self.align_selected(alignment);
+                    return;
+                }
             },
         }
 
+        if rl.is_key_pressed(KeyboardKey::KEY_DELETE) {
+            self.delete_cards(self.selected_cards.iter().cloned().collect());
+        }
+
         let mut post_handle_notification = None;
 
         // mouse update
@@ -1232,8 +2623,26 @@ impl CanvasScene {
             CanvasMouseState::Roaming => {
                 if rl.is_mouse_button_released(MouseButton::MOUSE_RIGHT_BUTTON) {
                     if self.last_r_mouse_pressed < 0.2 {
-                        self.context_menu.pos = self.get_mouse_world_pos(rl);
-                        self.context_menu.state = CanvasContextMenuState::NewCard;
+                        let m_pos = self.get_mouse_world_pos(rl);
+                        self.context_menu.pos = m_pos;
+
+                        // A quick right click on a connection deletes it
+                        match (self.get_card_at(m_pos), self.get_connection_at(m_pos)) {
+                            (Some(id), _) => {
+                                if !self.selected_cards.contains(&id) {
+                                    self.selected_cards.clear();
+                                    self.selected_cards.insert(id.clone());
+                                }
+                                self.context_menu.state = CanvasContextMenuState::CardOptions(id);
+                            }
+                            (None, Some((id, link_i))) => {
+                                self.history.record(self.snapshot());
+                                self.get_node_ref(&id).front_links[link_i] = "".to_string();
+                            }
+                            (None, None) => {
+                                self.context_menu.state = CanvasContextMenuState::NewCard;
+                            }
+                        }
                     }
                 }
             }
@@ -1248,7 +2657,9 @@ impl CanvasScene {
                     for c in &self.cards {
                         // found the card it will be linked to, it's c
                         // writing this code made my head hurt
-                        if self.get_mouse_world_pos(rl).distance_to(c.pos) < 10. {
+                        if c.card_type != NodeTypes::Start
+                            && self.get_mouse_world_pos(rl).distance_to(c.pos) < 10.
+                        {
                             found = c.node_ref.clone();
                             break;
                         }
@@ -1257,12 +2668,32 @@ impl CanvasScene {
                         // this is getting too confusing
                         let node_output_i = self
                             .copy_card_data(&ref_id)
+                            .unwrap()
                             .from_output_widget_i_to_node_front_link_i(&i);
 
                         self.get_node_ref(&ref_id.clone()).front_links[node_output_i] = found;
                     }
 
                     self.mouse_sate = CanvasMouseState::Roaming;
+                    self.history.end_edit(&self.snapshot());
+                }
+                return;
+            }
+            CanvasMouseState::PickingJumpTarget(id) => {
+                // Same as connections, it can be dragged or clicked twice
+                if rl.is_mouse_button_released(MouseButton::MOUSE_LEFT_BUTTON)
+                    && self.last_l_mouse_pressed > 0.5
+                {
+                    let id = id.clone();
+                    match self.get_card_at(self.get_mouse_world_pos(rl)) {
+                        Some(target) if target != id => {
+                            self.get_node_ref(&id).jump_target = Some(target);
+                        }
+                        _ => {}
+                    }
+
+                    self.mouse_sate = CanvasMouseState::Roaming;
+                    self.history.end_edit(&self.snapshot());
                 }
                 return;
             }
@@ -1272,14 +2703,28 @@ impl CanvasScene {
                 delta.scale(-1. / self.cam.zoom);
 
                 for c in &mut self.cards {
-                    if c.node_ref == id.as_str() {
+                    if c.node_ref == id.as_str() || self.selected_cards.contains(&c.node_ref) {
                         c.pos -= delta;
                     }
                 }
 
                 if rl.is_mouse_button_released(MouseButton::MOUSE_LEFT_BUTTON) {
                     self.mouse_sate = CanvasMouseState::Roaming;
+                    self.history.end_edit(&self.snapshot());
+                }
+            }
+            CanvasMouseState::BoxSelecting(start) => {
+                if rl.is_mouse_button_released(MouseButton::MOUSE_LEFT_BUTTON) {
+                    let (top_left, bottom_right) =
+                        box_corners(*start, self.get_mouse_world_pos(rl));
+                    for c in &self.cards {
+                        if c.overlaps(top_left, bottom_right) {
+                            self.selected_cards.insert(c.node_ref.clone());
+                        }
+                    }
+                    self.mouse_sate = CanvasMouseState::Roaming;
                 }
+                return;
             }
             _ => unimplemented!("{:?}", self.mouse_sate),
         }
@@ -1292,13 +2737,32 @@ impl CanvasScene {
         }
 
         let m_pos = self.get_mouse_world_pos(rl);
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+            let toggle = ctrl_down || shift_down;
+            self.update_selection(m_pos, toggle);
+            // So a toggled card doesn't also start being dragged or edited
+            if toggle {
+                return;
+            }
+        }
+
         for c in self.cards.iter_mut() {
             let notify = c.update(rl, m_pos.clone());
 
             match notify {
                 Some(notification_type) => match notification_type {
                     CardNotification::EditTextInput { id, node_member } => {
+                        let mut text = self.copy_node_data(&id).get_member_text(&node_member);
+                        // Jumps to a start are typed by its name, so that's what gets edited
+                        if node_member == NodeMember::JumpTarget {
+                            text = jump_target_text(&self.node_pool, &text);
+                        }
+                        self.text_input = TextEdit::new(&text);
+                        self.text_input_original = text;
+                        self.key_repeat = None;
+
                         self.state = CanvasSceneStates::EditingTextInput(id, node_member);
+                        self.history.begin_edit(self.snapshot());
                         return;
                     }
                     CardNotification::AddBranchToBranchesNode(id) => {
@@ -1309,20 +2773,44 @@ impl CanvasScene {
                         post_handle_notification =
                             Some(CardNotification::AddArgToEmitEventNode(id));
                     }
+                    CardNotification::AddOutputToRandomNode(id) => {
+                        post_handle_notification =
+                            Some(CardNotification::AddOutputToRandomNode(id));
+                    }
+                    CardNotification::PickingJumpTarget(id) => {
+                        self.mouse_sate = CanvasMouseState::PickingJumpTarget(id);
+                        self.history.begin_edit(self.snapshot());
+                        return;
+                    }
+                    CardNotification::GoToJumpTarget(id) => {
+                        let target = self.copy_node_data(&id).jump_target.unwrap();
+                        if target.is_empty() {
+                            self.notify_user("This jump doesn't go anywhere yet");
+                        } else {
+                            self.jump_to_card(&target);
+                        }
+                        return;
+                    }
                     CardNotification::ToggleCheckBox { id, node_member } => {
                         post_handle_notification =
                             Some(CardNotification::ToggleCheckBox { id, node_member });
                     }
+                    CardNotification::CycleSelector { id, node_member } => {
+                        post_handle_notification =
+                            Some(CardNotification::CycleSelector { id, node_member });
+                    }
                     CardNotification::CreatingCardConnection(id, i) => {
                         self.mouse_sate = CanvasMouseState::CreatingConnection(id.clone(), i);
 
                         let output_i = c.from_output_widget_i_to_node_front_link_i(&i);
+                        self.history.begin_edit(self.snapshot());
 
                         self.get_node_ref(&id).front_links[output_i] = "".to_string();
                         return;
                     }
                     CardNotification::MovingCard(id) => {
                         self.mouse_sate = CanvasMouseState::MovingCard(id);
+                        self.history.begin_edit(self.snapshot());
                         return;
                     }
                     _ => {
@@ -1333,19 +2821,18 @@ impl CanvasScene {
             }
         }
 
+        if post_handle_notification.is_some() {
+            self.history.record(self.snapshot());
+        }
+
         match post_handle_notification {
             None => {}
             Some(notification) => match notification {
                 CardNotification::AddBranchToBranchesNode(id) => {
-                    let pos = self.copy_card_data(&id).pos;
+                    let pos = self.copy_card_data(&id).unwrap().pos;
 
-                    let mut cur_node = self.get_node_ref(&id);
-                    let mut next_node_opt_vec = cur_node.branches.clone().unwrap();
-                    next_node_opt_vec.push("Empty".to_string());
-                    cur_node.branches = Some(next_node_opt_vec);
-                    let mut next_node_exit_vec = cur_node.front_links.clone();
-                    next_node_exit_vec.push("".to_string());
-                    cur_node.front_links = next_node_exit_vec;
+                    let cur_node = self.get_node_ref(&id);
+                    cur_node.push_branch("Empty".to_string());
 
                     let new_card = Card::new_branches(
                         cur_node.id.clone(),
@@ -1374,8 +2861,35 @@ impl CanvasScene {
                         _ => unimplemented!("{:?}", node_member),
                     }
                 }
+                CardNotification::CycleSelector { id, node_member } => {
+                    let cur_node = self.get_node_ref(&id);
+                    match node_member {
+                        NodeMember::Operation => {
+                            cur_node.operation = Some(cur_node.operation.unwrap().next());
+                        }
+                        NodeMember::RandomMode => {
+                            cur_node.random_mode = Some(cur_node.random_mode.unwrap().next());
+                        }
+                        NodeMember::BranchShowDisabled(i) => {
+                            let show_disabled = cur_node.branch_show_disabled.as_mut().unwrap();
+                            show_disabled[i] = !show_disabled[i];
+                        }
+                        _ => unimplemented!("{:?}", node_member),
+                    }
+                }
+                CardNotification::AddOutputToRandomNode(id) => {
+                    let pos = self.copy_card_data(&id).unwrap().pos;
+
+                    let cur_node = self.get_node_ref(&id);
+                    cur_node.push_random_output(1);
+                    let outputs = cur_node.weights.as_ref().unwrap().len();
+
+                    let new_card = Card::new_random(id.clone(), outputs, pos);
+                    let i = self.get_card_i(id);
+                    self.cards[i] = new_card;
+                }
                 CardNotification::AddArgToEmitEventNode(id) => {
-                    let pos = self.copy_card_data(&id).pos;
+                    let pos = self.copy_card_data(&id).unwrap().pos;
 
                     let mut cur_node = self.get_node_ref(&id);
                     let mut next_node_arg_vec = cur_node.event_data.clone().unwrap();
@@ -1443,139 +2957,329 @@ impl CanvasScene {
 
     pub fn draw(&self, d: &mut RaylibMode2D<'_, RaylibDrawHandle>) {
         for i in &self.cards {
-            i.draw(d, self.copy_node_data(&i.node_ref));
+            let selected = self.selected_cards.contains(&i.node_ref);
+            i.draw(
+                d,
+                &self.font,
+                self.copy_node_data(&i.node_ref),
+                &self.node_pool,
+                &self.variables,
+                selected,
+            );
         }
 
         self.draw_card_connections(d);
+
+        if let CanvasMouseState::BoxSelecting(start) = self.mouse_sate {
+            let (top_left, bottom_right) = box_corners(start, self.get_mouse_world_pos(d));
+            let size = bottom_right - top_left;
+            let rect = Rectangle {
+                x: top_left.x,
+                y: top_left.y,
+                width: size.x,
+                height: size.y,
+            };
+            d.draw_rectangle_rec(
+                rect,
+                Color {
+                    r: 255,
+                    g: 161,
+                    b: 0,
+                    a: 50,
+                },
+            );
+            d.draw_rectangle_lines_ex(rect, (2. / self.cam.zoom) as i32 + 1, Color::ORANGE);
+        }
+
         self.context_menu.draw(d, self.get_mouse_world_pos(d));
     }
 
-    // Yes, I diceded to go with some imediate ui here
-    fn update_and_draw_text_input_edit(
-        &mut self,
-        d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
-        tlp: Vector2,
-    ) {
-        match self.state {
-            CanvasSceneStates::EditingTextInput(_, _) => {}
-            _ => return,
+    // raylib 3.7 only reports the first press of a key, this also fires while it's held down
+    fn is_key_typed(&mut self, rl: &RaylibHandle, key: KeyboardKey) -> bool {
+        if rl.is_key_pressed(key) {
+            self.key_repeat = Some((key, 0.));
+            return true;
+        }
+
+        match &mut self.key_repeat {
+            Some((held, held_for)) if *held == key => {
+                if !rl.is_key_down(key) {
+                    self.key_repeat = None;
+                    return false;
+                }
+
+                let before = *held_for - KEY_REPEAT_DELAY;
+                *held_for += rl.get_frame_time();
+                let after = *held_for - KEY_REPEAT_DELAY;
+                if after < 0. {
+                    return false;
+                }
+                // Fires every time it goes past another KEY_REPEAT_RATE
+                before < 0. || (before / KEY_REPEAT_RATE) as i32 != (after / KEY_REPEAT_RATE) as i32
+            }
+            _ => false,
         }
+    }
 
-        // let keymap = [
-        //     ('A', KeyboardKey::KEY_A),
-        //     ('B', KeyboardKey::KEY_B),
-        //     ('C', KeyboardKey::KEY_C),
-        //     ('D', KeyboardKey::KEY_D),
-        //     ('E', KeyboardKey::KEY_E),
-        //     ('F', KeyboardKey::KEY_F),
-        //     ('G', KeyboardKey::KEY_G),
-        //     ('H', KeyboardKey::KEY_H),
-        //     ('I', KeyboardKey::KEY_I),
-        //     ('J', KeyboardKey::KEY_J),
-        //     ('K', KeyboardKey::KEY_K),
-        //     ('L', KeyboardKey::KEY_L),
-        //     ('M', KeyboardKey::KEY_M),
-        //     ('N', KeyboardKey::KEY_N),
-        //     ('O', KeyboardKey::KEY_O),
-        //     ('P', KeyboardKey::KEY_P),
-        //     ('Q', KeyboardKey::KEY_Q),
-        //     ('R', KeyboardKey::KEY_R),
-        //     ('S', KeyboardKey::KEY_S),
-        //     ('T', KeyboardKey::KEY_T),
-        //     ('U', KeyboardKey::KEY_U),
-        //     ('V', KeyboardKey::KEY_V),
-        //     ('W', KeyboardKey::KEY_W),
-        //     ('X', KeyboardKey::KEY_X),
-        //     ('Y', KeyboardKey::KEY_Y),
-        //     ('Z', KeyboardKey::KEY_Z),
-        // ];
-
-        let mut cur_text;
+    // Puts the text being edited wherever it goes, every frame so cards resize while typing
+    fn apply_text_input(&mut self, text: String) {
+        match self.state.clone() {
+            CanvasSceneStates::EditingTextInput(id, NodeMember::JumpTarget) => {
+                // Kept as the id as soon as the text names a start or a card
+                let target = document::find_jump_target(&self.node_pool, &text).unwrap_or(text);
+                self.get_node_ref(&id).jump_target = Some(target);
+            }
+            CanvasSceneStates::EditingTextInput(id, member) => {
+                self.get_node_ref(&id).set_member_text(&member, text);
+                self.fit_card_to_content(&id);
+            }
+            CanvasSceneStates::EditingVariable(i, VariableField::Name) => {
+                self.variables[i].name = text;
+            }
+            CanvasSceneStates::EditingVariable(i, VariableField::Default) => {
+                // Stays at the last value that made sense until the text is a valid one again
+                let var_type = self.variables[i].default.var_type();
+                if let Ok(value) = Value::parse(&text, var_type) {
+                    self.variables[i].default = value;
+                }
+            }
+            _ => {}
+        }
+    }
 
-        match &self.state {
-            CanvasSceneStates::EditingTextInput(wte, member) => match member {
-                NodeMember::Character => cur_text = self.copy_node_data(&wte).character.unwrap(),
-                NodeMember::Dialogue => cur_text = self.copy_node_data(&wte).dialogue.unwrap(),
-                NodeMember::Branch(i) => {
-                    let branches_vec = &self.copy_node_data(&wte).branches.unwrap();
-                    cur_text = branches_vec[*i].clone();
+    // Why the text being edited can't be kept, Enter does nothing until it's fixed
+    fn text_input_problem(&self) -> Option<String> {
+        let text = self.text_input.text();
+        match self.state {
+            CanvasSceneStates::EditingTextInput(_, NodeMember::ExitCode) => {
+                // Would be dropped otherwise, empty is fine and means no code
+                if !text.trim().is_empty() && text.trim().parse::<i64>().is_err() {
+                    return Some("The exit code has to be a whole number".to_string());
                 }
-                NodeMember::FlagToCheck => {
-                    cur_text = self.copy_node_data(&wte).flag_to_check.unwrap()
+                None
+            }
+            CanvasSceneStates::EditingTextInput(_, NodeMember::JumpTarget) => {
+                let found = document::find_jump_target(&self.node_pool, text);
+                if !text.trim().is_empty() && found.is_none() {
+                    return Some(format!("There is no start or card called {}", text.trim()));
                 }
-                NodeMember::FlagToSet => cur_text = self.copy_node_data(&wte).flag_to_set.unwrap(),
-                NodeMember::EventToEmit => {
-                    cur_text = self.copy_node_data(&wte).event_to_emit.unwrap()
+                None
+            }
+            CanvasSceneStates::EditingTextInput(_, NodeMember::Weight(_)) => {
+                match text.trim().parse::<i64>() {
+                    Ok(weight) if weight >= 0 => None,
+                    _ => Some("The weight has to be a whole number, 0 or more".to_string()),
                 }
-                NodeMember::EventDataKey(i) => {
-                    let args_vec = &self.copy_node_data(&wte).event_data.unwrap();
-                    cur_text = args_vec[*i].clone().0;
+            }
+            CanvasSceneStates::EditingVariable(i, VariableField::Name) => {
+                let name = text.trim();
+                if name.is_empty() {
+                    return Some("The variable needs a name".to_string());
                 }
-                NodeMember::EventDataVal(i) => {
-                    let args_vec = &self.copy_node_data(&wte).event_data.unwrap();
-                    cur_text = args_vec[*i].clone().1;
+                if !expression::is_name(name) {
+                    return Some(format!(
+                        "Conditions can't read {}, use letters, digits and _, no digit first or keyword",
+                        name
+                    ));
                 }
-                _ => unimplemented!("{:?}", member),
-            },
-            _ => panic!("Something has gone incredibly wrong."),
+                let taken = self
+                    .variables
+                    .iter()
+                    .enumerate()
+                    .any(|(other_i, v)| other_i != i && v.name == name);
+                if taken {
+                    return Some(format!("There already is a variable called {}", name));
+                }
+                None
+            }
+            CanvasSceneStates::EditingVariable(i, VariableField::Default) => {
+                match Value::parse(text, self.variables[i].default.var_type()) {
+                    Ok(_) => None,
+                    Err(err) => Some(format!("The default {}", err)),
+                }
+            }
+            _ => None,
         }
+    }
 
-        // I had to copy the code from d.get_key_pressed() since it simply doesn't work
-        let pressed_key;
-        let key = unsafe { ffi::GetKeyPressed() };
-        if key > 0 {
-            pressed_key = key_from_i32(key);
-        } else {
-            pressed_key = None
+    // Set variable cards refer to variables by name, so they're changed along with it
+    fn finish_variable_rename(&mut self) {
+        let i = match self.state {
+            CanvasSceneStates::EditingVariable(i, VariableField::Name) => i,
+            _ => return,
+        };
+        let old_name = self.text_input_original.trim().to_string();
+        let new_name = self.variables[i].name.trim().to_string();
+        self.variables[i].name = new_name.clone();
+        if old_name.is_empty() || old_name == new_name {
+            return;
         }
 
-        match pressed_key {
-            Some(key) => {
-                let to_ascii = key as u8 + 32;
-                if to_ascii >= 97 && to_ascii <= 123 {
-                    cur_text.push(to_ascii as char);
-                }}
-            None => {}
-        }
+        let mut renamed = vec![];
+        for n in self.node_pool.iter_mut() {
+            if n.node_type == NodeTypes::SetVariable
+                && n.variable.as_deref().map(|v| v.trim()) == Some(old_name.as_str())
+            {
+                n.variable = Some(new_name.clone());
+                renamed.push(n.id.clone());
+            }
 
-        match &self.state {
-            CanvasSceneStates::EditingTextInput(id, member) => {
-                for i in &mut self.node_pool {
-                    if i.id == id.as_str() {
-                        match member {
-                            NodeMember::Dialogue => i.dialogue = Some(cur_text.clone()),
-                            NodeMember::Character => i.character = Some(cur_text.clone()),
-                            NodeMember::Branch(opt_i) => {
-                                let mut cur_vec = i.branches.clone().unwrap();
-                                cur_vec[*opt_i] = cur_text.clone();
-                                i.branches = Some(cur_vec);
-                            }
-                            NodeMember::FlagToCheck => i.flag_to_check = Some(cur_text.clone()),
-                            NodeMember::FlagToSet => i.flag_to_set = Some(cur_text.clone()),
-                            NodeMember::EventToEmit => i.event_to_emit = Some(cur_text.clone()),
-                            NodeMember::EventDataKey(arg_i) => {
-                                let mut cur_vec = i.event_data.clone().unwrap();
-                                cur_vec[*arg_i].0 = cur_text.clone();
-                                i.event_data = Some(cur_vec);
-                            }
-                            NodeMember::EventDataVal(arg_i) => {
-                                let mut cur_vec = i.event_data.clone().unwrap();
-                                cur_vec[*arg_i].1 = cur_text.clone();
-                                i.event_data = Some(cur_vec);
-                            }
-                            _ => unimplemented!("{:?}", member),
-                        }
-                    }
+            // Conditions read it too
+            let mut conditions: Vec<&mut String> = n.condition.iter_mut().collect();
+            conditions.extend(n.branch_conditions.iter_mut().flatten());
+            let mut changed = false;
+            for condition in conditions {
+                let new_condition = expression::rename(condition, &old_name, &new_name);
+                if *condition != new_condition {
+                    *condition = new_condition;
+                    changed = true;
                 }
             }
-            _ => panic!("Something has gone incredibly wrong."),
+            if changed {
+                renamed.push(n.id.clone());
+            }
         }
+        for id in renamed {
+            self.fit_card_to_content(&id);
+        }
+    }
 
-        if d.is_key_pressed(KeyboardKey::KEY_ENTER) {
+    // Yes, I diceded to go with some imediate ui here
+    fn update_and_draw_text_input_edit(
+        &mut self,
+        d: &mut RaylibMode2D<'_, RaylibDrawHandle>,
+        tlp: Vector2,
+    ) {
+        // Only dialogue can have more than one line
+        let multiline = match &self.state {
+            CanvasSceneStates::EditingTextInput(_, member) => *member == NodeMember::Dialogue,
+            CanvasSceneStates::EditingVariable(_, _) => false,
+            _ => return,
+        };
+
+        let ctrl_down = d.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
+            || d.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+        let shift_down = d.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
+            || d.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+
+        if d.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            // Puts back what was there before, so there's nothing left to undo either
+            let original = self.text_input_original.clone();
+            self.apply_text_input(original);
             self.state = CanvasSceneStates::Roaming;
+            self.history.end_edit(&self.snapshot());
             return;
         }
 
+        if d.is_key_pressed(KeyboardKey::KEY_ENTER) {
+            if multiline && shift_down {
+                self.text_input.insert_str("\n");
+            } else if let Some(problem) = self.text_input_problem() {
+                self.notify_user(problem);
+            } else {
+                self.finish_variable_rename();
+                self.state = CanvasSceneStates::Roaming;
+                self.history.end_edit(&self.snapshot());
+                return;
+            }
+        }
+
+        // Chars come already translated with the keyboard layout, shift, dead keys and so on
+        loop {
+            let codepoint = unsafe { ffi::GetCharPressed() };
+            if codepoint <= 0 {
+                break;
+            }
+            if let Some(c) = char::from_u32(codepoint as u32) {
+                self.text_input.insert_char(c);
+            }
+        }
+
+        if ctrl_down && d.is_key_pressed(KeyboardKey::KEY_A) {
+            self.text_input.select_all();
+        }
+        if ctrl_down && d.is_key_pressed(KeyboardKey::KEY_C) {
+            if let Some(selected) = self.text_input.selected_text() {
+                set_clipboard_text(selected);
+            }
+        }
+        if ctrl_down && d.is_key_pressed(KeyboardKey::KEY_X) {
+            if let Some(removed) = self.text_input.delete_selection() {
+                set_clipboard_text(&removed);
+            }
+        }
+        if ctrl_down && d.is_key_pressed(KeyboardKey::KEY_V) {
+            match d.get_clipboard_text() {
+                Ok(pasted) => {
+                    let pasted: String = pasted
+                        .replace("\r\n", "\n")
+                        .chars()
+                        .filter(|c| !c.is_control() || (multiline && *c == '\n'))
+                        .collect();
+                    self.text_input.insert_str(&pasted);
+                }
+                Err(err) => println!("CLIPBOARD_ERR: {}", err),
+            }
+        }
+
+        if self.is_key_typed(d, KeyboardKey::KEY_BACKSPACE) {
+            self.text_input.backspace(ctrl_down);
+        }
+        if self.is_key_typed(d, KeyboardKey::KEY_DELETE) {
+            self.text_input.delete(ctrl_down);
+        }
+        if self.is_key_typed(d, KeyboardKey::KEY_LEFT) {
+            self.text_input.move_left(ctrl_down, shift_down);
+        }
+        if self.is_key_typed(d, KeyboardKey::KEY_RIGHT) {
+            self.text_input.move_right(ctrl_down, shift_down);
+        }
+
+        let up_typed = self.is_key_typed(d, KeyboardKey::KEY_UP);
+        let down_typed = self.is_key_typed(d, KeyboardKey::KEY_DOWN);
+
+        let font_size = 24;
+        let line_height = 28;
+        let measure = |line: &str| self.font.measure_text(line, font_size);
+
+        // Up, down, home and end work on the lines as they are shown, so they need the wrapping
+        let lines = text_edit::wrap_text(self.text_input.text(), 1230, measure);
+        let caret_line = text_edit::line_of(&lines, self.text_input.caret());
+
+        let mut go_to_line = None;
+        if up_typed && caret_line > 0 {
+            go_to_line = Some(caret_line - 1);
+        }
+        if down_typed && caret_line + 1 < lines.len() {
+            go_to_line = Some(caret_line + 1);
+        }
+        if let Some(line_i) = go_to_line {
+            let text = self.text_input.text();
+            let (start, _) = lines[caret_line];
+            let caret_x = measure(&text[start..self.text_input.caret()]);
+            let to = closest_to_x(text, lines[line_i], caret_x, measure);
+            self.text_input.move_to(to, shift_down);
+        }
+
+        if d.is_key_pressed(KeyboardKey::KEY_HOME) {
+            if ctrl_down {
+                self.text_input.home(shift_down);
+            } else {
+                self.text_input.move_to(lines[caret_line].0, shift_down);
+            }
+        }
+        if d.is_key_pressed(KeyboardKey::KEY_END) {
+            if ctrl_down {
+                self.text_input.end(shift_down);
+            } else {
+                self.text_input.move_to(lines[caret_line].1, shift_down);
+            }
+        }
+
+        let cur_text = self.text_input.text().to_string();
+        self.apply_text_input(cur_text.clone());
+        self.font.add_chars(&cur_text);
+
         d.draw_rectangle(
             (tlp.x) as i32 - 10,
             (tlp.y) as i32 - 10,
@@ -1596,68 +3300,83 @@ impl CanvasScene {
             Color::WHITE,
         );
 
-        d.draw_text(
-            &cur_text,
-            (tlp.x) as i32 + 20,
-            (tlp.y) as i32 + 20,
-            24,
-            Color::BLACK,
-        );
+        let text_x = (tlp.x) as i32 + 20;
+        let text_y = (tlp.y) as i32 + 20;
+
+        // The text may have changed since the keys were handled
+        let measure = |line: &str| self.font.measure_text(line, font_size);
+        let lines = text_edit::wrap_text(&cur_text, 1230, measure);
+        let caret = self.text_input.caret();
+        let selection = self.text_input.selection();
+
+        for (line_i, (start, end)) in lines.iter().enumerate() {
+            let line_y = text_y + line_i as i32 * line_height;
+
+            match selection {
+                Some((sel_start, sel_end)) if sel_start <= *end && sel_end >= *start => {
+                    let from = sel_start.max(*start);
+                    let to = sel_end.min(*end);
+                    let from_x = measure(&cur_text[*start..from]);
+                    let to_x = measure(&cur_text[*start..to]);
+                    d.draw_rectangle(
+                        text_x + from_x,
+                        line_y,
+                        (to_x - from_x).max(4),
+                        font_size,
+                        Color::SKYBLUE,
+                    );
+                }
+                _ => {}
+            }
+
+            self.font.draw_text(
+                d,
+                &cur_text[*start..*end],
+                text_x,
+                line_y,
+                font_size,
+                Color::BLACK,
+            );
+        }
+
+        // Blinks twice a second
+        if (d.get_time() * 2.) as i64 % 2 == 0 {
+            let caret_line = text_edit::line_of(&lines, caret);
+            let (start, _) = lines[caret_line];
+            let caret_x = text_x + measure(&cur_text[start..caret]);
+            let caret_y = text_y + caret_line as i32 * line_height;
+            d.draw_line(caret_x, caret_y, caret_x, caret_y + font_size, Color::BLACK);
+        }
     }
 
     pub fn parse_node_pool(&mut self) {
         println!("Parsing node_pool");
 
+        for i in &self.node_pool {
+            self.font.add_node_chars(i);
+        }
+        for v in &self.variables {
+            self.font.add_chars(&v.name);
+            self.font.add_chars(&v.default.to_string());
+        }
+
         let mut x_offset = 0.;
         for i in &self.node_pool {
-            match i.node_type {
-                NodeTypes::Dialogue => {
-                    let card_pos = Vector2 { x: x_offset, y: 0. };
-                    self.cards.push(Card::new_dialogue(i.id.clone(), card_pos));
-                    x_offset += 200.;
-                }
-                NodeTypes::Branches => {
-                    let card_pos = Vector2 { x: x_offset, y: 0. };
-                    self.cards.push(Card::new_branches(
-                        i.id.clone(),
-                        i.clone().branches.unwrap(),
-                        card_pos,
-                    ));
-                    x_offset += 200.;
-                }
-                NodeTypes::Conditional => {
-                    let card_pos = Vector2 { x: x_offset, y: 0. };
-                    self.cards
-                        .push(Card::new_conditional(i.id.clone(), card_pos));
-                    x_offset += 200.;
-                }
-                NodeTypes::SetFlag => {
-                    let card_pos = Vector2 { x: x_offset, y: 0. };
-                    self.cards.push(Card::new_set_flag(i.id.clone(), card_pos));
-                    x_offset += 200.;
-                }
-                NodeTypes::EmitEvent => {
-                    let card_pos = Vector2 { x: x_offset, y: 0. };
-                    self.cards.push(Card::new_emit_event(
-                        i.id.clone(),
-                        i.event_data.clone().unwrap(),
-                        card_pos,
-                    ));
-                    x_offset += 200.;
-                }
-                _ => unimplemented!("{:?}", i.node_type),
-            }
+            let card_pos = Vector2 { x: x_offset, y: 0. };
+            self.cards.push(Card::from_node(i, card_pos));
+            x_offset += 200.;
         }
     }
 
-    fn copy_card_data(&self, id: &String) -> Card {
+    // None when there's no card for the id, links can point at nodes that don't exist anymore
+    fn copy_card_data(&self, id: &str) -> Option<Card> {
         for i in &self.cards {
-            if i.node_ref == id.as_str() {
-                return i.clone();
+            if i.node_ref == id {
+                return Some(i.clone());
             }
         }
 
-        unreachable!()
+        None
     }
     fn copy_node_data(&self, id: &String) -> Node {
         for i in &self.node_pool {
@@ -1670,8 +3389,23 @@ impl CanvasScene {
     }
 
     fn draw_card_connections(&self, d: &mut RaylibMode2D<RaylibDrawHandle>) {
+        // Only shown when nothing else would take the right click
+        let hovered_connection = match (&self.mouse_sate, &self.context_menu.state) {
+            (CanvasMouseState::Roaming, CanvasContextMenuState::Hidden) => {
+                let m_pos = self.get_mouse_world_pos(d);
+                match self.get_card_at(m_pos) {
+                    Some(_) => None,
+                    None => self.get_connection_at(m_pos),
+                }
+            }
+            _ => None,
+        };
+
         for i in &self.node_pool {
-            let i_card = self.copy_card_data(&i.id);
+            let i_card = match self.copy_card_data(&i.id) {
+                Some(card) => card,
+                None => continue,
+            };
             let outputs = i_card.copy_output_widgets();
 
             if i.front_links.len() != outputs.len() {
@@ -1686,28 +3420,116 @@ impl CanvasScene {
                 }
 
                 let start_pos = i_card.pos + outputs[j].offset;
-                let end_pos = self.copy_card_data(&i.front_links[j]).pos;
-                d.draw_line_ex(start_pos, end_pos, 5., Color::PURPLE);
+                let end_pos = match self.copy_card_data(&i.front_links[j]) {
+                    Some(card) => card.pos,
+                    None => continue,
+                };
+                let color = match &hovered_connection {
+                    Some((id, link_i)) if *id == i.id && *link_i == j => Color::RED,
+                    _ => Color::PURPLE,
+                };
+                d.draw_line_ex(start_pos, end_pos, 5., color);
             }
         }
 
         match &self.mouse_sate {
             CanvasMouseState::CreatingConnection(id, i) => {
-                let start_pos = self.copy_card_data(id).pos
-                    + self
-                        .copy_card_data(id)
-                        .widgets
-                        .get(i.clone())
-                        .unwrap()
-                        .offset;
+                let card = self.copy_card_data(id).unwrap();
+                let start_pos = card.pos + card.widgets.get(*i).unwrap().offset;
                 let end_pos = self.get_mouse_world_pos(d);
                 d.draw_line_ex(start_pos, end_pos, 5., Color::PURPLE);
             }
+            CanvasMouseState::PickingJumpTarget(id) => {
+                let start_pos = self.copy_card_data(id).unwrap().pos + JUMP_PICKER_OFFSET;
+                let end_pos = self.get_mouse_world_pos(d);
+                d.draw_line_ex(start_pos, end_pos, 3., Color::SKYBLUE);
+            }
             _ => {}
         }
     }
 }
 
+// What a jump card shows for its target, ids mean nothing to whoever is writing the dialogue
+fn jump_target_label(nodes: &[Node], target: &str) -> String {
+    if target.is_empty() {
+        return "(no target)".to_string();
+    }
+    match nodes.iter().find(|n| n.id == target) {
+        Some(n) => match n.node_type {
+            NodeTypes::Start => n.entry_name.clone().unwrap(),
+            NodeTypes::Dialogue => format!(
+                "{}: {}",
+                n.character.clone().unwrap(),
+                n.dialogue.clone().unwrap()
+            ),
+            _ => format!("{:?} {}", n.node_type, n.id),
+        },
+        None => format!("{} (missing)", target),
+    }
+}
+
+// The text a jump's target is edited as, the name for starts and the id for anything else
+fn jump_target_text(nodes: &[Node], target: &str) -> String {
+    match nodes.iter().find(|n| n.id == target) {
+        Some(n) if n.node_type == NodeTypes::Start => n.entry_name.clone().unwrap(),
+        _ => target.to_string(),
+    }
+}
+
+// raylib only lets the clipboard be set through a mutable handle, which drawing code doesn't have
+fn set_clipboard_text(text: &str) {
+    match std::ffi::CString::new(text) {
+        Ok(text) => unsafe { ffi::SetClipboardText(text.as_ptr()) },
+        Err(_) => println!("CLIPBOARD_ERR: Text has a nul character"),
+    }
+}
+
+// Byte index in the line that is closest to x, for moving the caret up and down
+fn closest_to_x(
+    text: &str,
+    (start, end): (usize, usize),
+    x: i32,
+    measure: impl Fn(&str) -> i32,
+) -> usize {
+    let mut to_return = start;
+    let mut best_distance = i32::MAX;
+    for (i, _) in text[start..end]
+        .char_indices()
+        .chain(std::iter::once((end - start, ' ')))
+    {
+        let distance = (measure(&text[start..start + i]) - x).abs();
+        if distance < best_distance {
+            best_distance = distance;
+            to_return = start + i;
+        }
+    }
+    to_return
+}
+
+fn box_corners(a: Vector2, b: Vector2) -> (Vector2, Vector2) {
+    (
+        Vector2 {
+            x: a.x.min(b.x),
+            y: a.y.min(b.y),
+        },
+        Vector2 {
+            x: a.x.max(b.x),
+            y: a.y.max(b.y),
+        },
+    )
+}
+
+fn distance_to_segment(point: Vector2, start: Vector2, end: Vector2) -> f32 {
+    let segment = end - start;
+    let length_sqr = segment.length_sqr();
+    if length_sqr == 0. {
+        return point.distance_to(start);
+    }
+
+    let t = ((point - start).dot(segment) / length_sqr).clamp(0., 1.);
+    point.distance_to(start + segment.scale_by(t))
+}
+
 fn main() {
     let (mut rl, thread) = raylib::init()
         .size(1280, 720)
@@ -1715,6 +3537,8 @@ fn main() {
         .build();
 
     rl.set_target_fps(60);
+    // Escape cancels text edits, it should not close the whole editor
+    rl.set_exit_key(None);
 
     // TODO: new() function for context menu
     let mut cm_images = HashMap::new();
@@ -1727,6 +3551,11 @@ fn main() {
         rl.load_texture_from_image(&thread, &new_card_image)
             .unwrap(),
     );
+    let undo_depth = match std::env::var("DIALOGUE_MAKER_UNDO_DEPTH") {
+        Ok(val) => val.parse().unwrap_or(DEFAULT_UNDO_DEPTH),
+        Err(_) => DEFAULT_UNDO_DEPTH,
+    };
+
     let mut canvas_scene = CanvasScene {
         cam: Camera2D {
             offset: Vector2 {
@@ -1792,6 +3621,7 @@ fn main() {
             //     vec!["00001".to_string()],
             // ),
         ],
+        variables: vec![],
         state: CanvasSceneStates::Roaming,
         mouse_sate: CanvasMouseState::Roaming,
         last_l_mouse_pressed: 0.,
@@ -1801,15 +3631,56 @@ fn main() {
             pos: Vector2 { x: 0., y: 0. },
             images: cm_images,
         },
+        selected_cards: HashSet::new(),
+        history: History::new(undo_depth),
+        font: EditorFont::new(std::env::var("DIALOGUE_MAKER_FONT").ok()),
+        file_path: None,
+        saved: CanvasSnapshot {
+            node_pool: vec![],
+            variables: vec![],
+            cards: vec![],
+        },
+        text_input: TextEdit::default(),
+        text_input_original: "".to_string(),
+        key_repeat: None,
+        notice: None,
+        problems: vec![],
+        problems_panel_open: false,
+        problems_scroll: 0,
+        problems_age: 0.,
+        variables_panel_open: false,
+        variables_scroll: 0,
     };
     canvas_scene.parse_node_pool();
 
+    // `dialogue_maker path/to/file.json` opens it right away. A path that doesn't exist yet is
+    // where the new project will be saved.
+    if let Some(path) = std::env::args().nth(1) {
+        if std::path::Path::new(&path).exists() {
+            canvas_scene.open_file(path);
+        } else {
+            println!(
+                "LOAD_FILE_INFO: {} doesn't exist yet, it will be created on save",
+                path
+            );
+            canvas_scene.file_path = Some(path);
+        }
+    }
+    let mut window_title = "".to_string();
+
     // Raylib in rust for some reason doesn't provide a get_mouse_delta funcion, so the program will do it ny itself
     let mut last_mouse_pos = rl.get_mouse_position();
 
     while !rl.window_should_close() {
         // ===== UPDATE =====
         canvas_scene.update(&rl, &mut last_mouse_pos);
+        canvas_scene.reload_font_if_needed(&mut rl, &thread);
+
+        let new_title = canvas_scene.window_title();
+        if new_title != window_title {
+            rl.set_window_title(&thread, &new_title);
+            window_title = new_title;
+        }
 
         let tlp = rl.get_screen_to_world2D(Vector2 { x: 0., y: 0. }, canvas_scene.cam);
         let trp = rl.get_screen_to_world2D(Vector2 { x: 1280., y: 0. }, canvas_scene.cam);
@@ -1828,7 +3699,14 @@ fn main() {
         // ===== IMGUI LIKE PART =====
         canvas_scene.update_and_draw_text_input_edit(&mut new_d, tlp); // Runs only if canvas state is EditingTextInput
 
+        canvas_scene.draw_notice(&mut new_d, tlp);
+
         // new_d.draw_text("Hello, world!", 12, 12, 20, Color::BLACK);
         new_d.draw_fps(tlp.x as i32, tlp.y as i32);
+
+        // Back to screen coordinates
+        drop(new_d);
+        canvas_scene.draw_problems_panel(&mut d);
+        canvas_scene.draw_variables_panel(&mut d);
     }
 }
@@ -0,0 +1,304 @@
+// Brings files saved by older versions up to the current format, one version at a time, so
+// Document::from_json only ever has to understand the newest one.
+//
+// 1: nodes straight in the root, branches as an object of text to next
+// 2: nodes inside "nodes" next to the editor's "editor" data
+// 3: branches as an array of {text, next}, the version is saved in "version"
+// 4: start and end nodes
+// 5: typed variables, declared in "variables", and set variable nodes
+// 6: conditionals check a "condition" expression instead of a "flag_to_check"
+// 7: branches can have a "condition" and "show_disabled"
+// 8: random nodes
+// 9: jump nodes
+// 10: nodes straight in the root again, next to "version", "variables" and "editor", so game
+//     code that reads node ids from the root like it did with version 1 keeps working
+use json_parser::{JsonError, JsonObject, JsonType};
+
+use crate::expression;
+
+pub const FORMAT_VERSION: i64 = 10;
+
+// Each one takes a document of the version it's at in the list plus one
+const MIGRATIONS: [fn(JsonObject) -> JsonObject; 9] = [
+    wrap_nodes,
+    branches_to_array,
+    only_additions,
+    only_additions,
+    flags_to_conditions,
+    only_additions,
+    only_additions,
+    only_additions,
+    unwrap_nodes,
+];
+
+// Files from before the version was saved are told apart by their layout. None when there is
+// a version but it isn't one any file was ever saved with.
+pub fn get_version(obj: &JsonObject) -> Option<i64> {
+    match obj.get_integer("version") {
+        Ok(version) if version >= 1 => Some(version),
+        Err(JsonError::KeyNotFound) => match obj.get_obj("nodes") {
+            Ok(_) => Some(2),
+            Err(_) => Some(1),
+        },
+        _ => None,
+    }
+}
+
+// Doesn't check the version is supported, that's up to the caller
+pub fn migrate(mut obj: JsonObject, from_version: i64) -> JsonObject {
+    for migration in MIGRATIONS.iter().skip((from_version - 1) as usize) {
+        obj = migration(obj);
+    }
+
+    obj.set_integer("version", FORMAT_VERSION);
+    obj
+}
+
+fn wrap_nodes(root: JsonObject) -> JsonObject {
+    let mut to_return = JsonObject::new();
+    to_return.push_obj("nodes");
+    *to_return.get_obj_ref("nodes").unwrap() = root;
+    to_return
+}
+
+fn unwrap_nodes(obj: JsonObject) -> JsonObject {
+    let mut to_return = JsonObject::new();
+    for (key, value) in obj.children {
+        match (key.as_str(), value) {
+            ("nodes", JsonType::Object(nodes)) => to_return.children.extend(nodes.children),
+            (_, value) => to_return.children.push((key, value)),
+        }
+    }
+    to_return
+}
+
+fn branches_to_array(mut obj: JsonObject) -> JsonObject {
+    let nodes = match obj.get_obj_ref("nodes") {
+        Ok(nodes) => nodes,
+        Err(_) => return obj,
+    };
+
+    for (_, node) in nodes.children.iter_mut() {
+        let node = match node {
+            JsonType::Object(node) => node,
+            _ => continue,
+        };
+        let old_branches = match node.get_obj("branches") {
+            Ok(old_branches) => old_branches,
+            Err(_) => continue,
+        };
+
+        let mut branches = vec![];
+        for (text, next) in old_branches.children {
+            let mut branch = JsonObject::new();
+            branch.set_string("text".to_string(), text);
+            branch.children.push(("next".to_string(), next));
+            branches.push(JsonType::Object(branch));
+        }
+        node.set_array("branches", branches);
+    }
+
+    obj
+}
+
+// A condition that's only the flag does what checking the flag did, unset flags included
+fn flags_to_conditions(mut obj: JsonObject) -> JsonObject {
+    let variable_names: Vec<String> = match obj.get_obj("variables") {
+        Ok(variables) => variables
+            .children
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect(),
+        Err(_) => vec![],
+    };
+    let nodes = match obj.get_obj_ref("nodes") {
+        Ok(nodes) => nodes,
+        Err(_) => return obj,
+    };
+
+    for (_, node) in nodes.children.iter_mut() {
+        let node = match node {
+            JsonType::Object(node) => node,
+            _ => continue,
+        };
+        for (key, value) in node.children.iter_mut() {
+            if key != "flag_to_check" {
+                continue;
+            }
+            if let JsonType::String(flag) = value {
+                let condition = match flag.is_empty() {
+                    true => "".to_string(),
+                    false => expression::flag_reference(flag, &variable_names),
+                };
+                *value = JsonType::String(condition);
+            }
+            *key = "condition".to_string();
+        }
+    }
+
+    obj
+}
+
+// For versions that only added things, older files are already valid. The bump is so older
+// versions refuse the newer files instead of failing on something they don't know.
+fn only_additions(obj: JsonObject) -> JsonObject {
+    obj
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::{Document, LoadError};
+    use json_parser::Parser;
+
+    fn json(text: &str) -> JsonObject {
+        let mut parser = Parser::new();
+        parser.set_keep_integers(true);
+        parser.load(text);
+        parser.parse().unwrap()
+    }
+
+    // Compared as text so a failure shows the whole file
+    fn assert_migrates(from: &str, from_version: i64, expected: &str) {
+        let migrated = migrate(json(from), from_version);
+        assert_eq!(migrated.stringify(), json(expected).stringify());
+    }
+
+    #[test]
+    fn versions_come_from_the_file_or_its_layout() {
+        assert_eq!(get_version(&json("{\"00001\": {}}")), Some(1));
+        assert_eq!(get_version(&json("{}")), Some(1));
+        assert_eq!(
+            get_version(&json("{\"nodes\": {}, \"editor\": {}}")),
+            Some(2)
+        );
+        assert_eq!(
+            get_version(&json("{\"version\": 7, \"nodes\": {}}")),
+            Some(7)
+        );
+        assert_eq!(get_version(&json("{\"version\": 1}")), Some(1));
+        for version in ["0", "-1", "1.5", "\"3\"", "null", "{}"] {
+            let text = format!("{{\"version\": {}}}", version);
+            assert_eq!(get_version(&json(&text)), None, "{}", version);
+        }
+    }
+
+    #[test]
+    fn newer_and_broken_versions_are_refused() {
+        assert!(matches!(
+            Document::load_from_str("{\"version\": 11}"),
+            Err(LoadError::NewerVersion(11))
+        ));
+        assert!(matches!(
+            Document::load_from_str("{\"version\": 0}"),
+            Err(LoadError::InvalidVersion)
+        ));
+        let current = format!("{{\"version\": {}}}", FORMAT_VERSION);
+        assert_eq!(
+            Document::load_from_str(&current).unwrap().upgraded_from,
+            None
+        );
+    }
+
+    #[test]
+    fn version_1_nodes_get_wrapped() {
+        let v1 = "{\"00001\": {\"type\": \"dialogue\", \"next\": \"\"}}";
+        assert_eq!(
+            wrap_nodes(json(v1)).stringify(),
+            json(&format!("{{\"nodes\": {}}}", v1)).stringify()
+        );
+    }
+
+    #[test]
+    fn version_2_branches_become_an_array_in_order() {
+        let v2 = r#"{"nodes": {
+            "00001": {"type": "branches", "branches": {"Yes": "00002", "No": ""}},
+            "00002": {"type": "dialogue", "next": ""}
+        }, "editor": {}}"#;
+        let v3 = r#"{"nodes": {
+            "00001": {"type": "branches", "branches": [
+                {"text": "Yes", "next": "00002"},
+                {"text": "No", "next": ""}
+            ]},
+            "00002": {"type": "dialogue", "next": ""}
+        }, "editor": {}}"#;
+        assert_eq!(
+            branches_to_array(json(v2)).stringify(),
+            json(v3).stringify()
+        );
+    }
+
+    #[test]
+    fn version_5_flags_become_conditions() {
+        let v5 = r#"{"version": 5, "nodes": {
+            "00001": {"type": "conditional", "flag_to_check": "has_key",
+                      "if": {"true": "", "false": "", "not_set": ""}},
+            "00002": {"type": "conditional", "flag_to_check": "gold", "if": {}},
+            "00003": {"type": "conditional", "flag_to_check": "met \"Bob\"", "if": {}},
+            "00004": {"type": "conditional", "flag_to_check": "", "if": {}}
+        }, "variables": {"gold": {"type": "int", "default": 0}}}"#;
+        let v6 = r#"{"version": 5, "nodes": {
+            "00001": {"type": "conditional", "condition": "has_key",
+                      "if": {"true": "", "false": "", "not_set": ""}},
+            "00002": {"type": "conditional", "condition": "flag(\"gold\")", "if": {}},
+            "00003": {"type": "conditional", "condition": "flag(\"met \\\"Bob\\\"\")", "if": {}},
+            "00004": {"type": "conditional", "condition": "", "if": {}}
+        }, "variables": {"gold": {"type": "int", "default": 0}}}"#;
+        assert_eq!(
+            flags_to_conditions(json(v5)).stringify(),
+            json(v6).stringify()
+        );
+    }
+
+    #[test]
+    fn version_9_nodes_go_back_to_the_root() {
+        let v9 = r#"{"version": 9, "nodes": {"00001": {"type": "end"}, "00002": {"type": "end"}},
+            "variables": {}, "editor": {"cards": {}}}"#;
+        let v10 = r#"{"version": 9, "00001": {"type": "end"}, "00002": {"type": "end"},
+            "variables": {}, "editor": {"cards": {}}}"#;
+        assert_eq!(unwrap_nodes(json(v9)).stringify(), json(v10).stringify());
+    }
+
+    #[test]
+    fn version_1_files_come_out_as_the_current_version() {
+        let v1 = r#"{
+            "00001": {"type": "conditional", "flag_to_check": "has key",
+                      "if": {"true": "00002", "false": "", "not_set": "00003"}},
+            "00002": {"type": "branches", "branches": {"Open": "00003", "Leave": ""}},
+            "00003": {"type": "dialogue", "character": "Bob", "dialogue": "Hi", "next": ""}
+        }"#;
+        let v10 = r#"{
+            "00001": {"type": "conditional", "condition": "flag(\"has key\")",
+                      "if": {"true": "00002", "false": "", "not_set": "00003"}},
+            "00002": {"type": "branches", "branches": [
+                {"text": "Open", "next": "00003"},
+                {"text": "Leave", "next": ""}
+            ]},
+            "00003": {"type": "dialogue", "character": "Bob", "dialogue": "Hi", "next": ""},
+            "version": 10
+        }"#;
+        assert_migrates(v1, 1, v10);
+        assert_eq!(FORMAT_VERSION, 10, "add the new step to the tests");
+    }
+
+    #[test]
+    fn later_versions_only_get_the_steps_after_them() {
+        // Already arrays and conditions, so only the nodes move
+        let v7 = r#"{"version": 7, "nodes": {
+            "00001": {"type": "conditional", "condition": "gold > 1",
+                      "if": {"true": "", "false": "", "not_set": ""}},
+            "00002": {"type": "branches", "branches": [
+                {"text": "Yes", "next": "", "condition": "gold > 1", "show_disabled": true}
+            ]}
+        }, "variables": {"gold": {"type": "int", "default": 0}}, "editor": {"cards": {}}}"#;
+        let v10 = r#"{"version": 10,
+            "00001": {"type": "conditional", "condition": "gold > 1",
+                      "if": {"true": "", "false": "", "not_set": ""}},
+            "00002": {"type": "branches", "branches": [
+                {"text": "Yes", "next": "", "condition": "gold > 1", "show_disabled": true}
+            ]},
+            "variables": {"gold": {"type": "int", "default": 0}}, "editor": {"cards": {}}}"#;
+        assert_migrates(v7, 7, v10);
+        assert_migrates(v10, 10, v10);
+    }
+}
@@ -0,0 +1,491 @@
+use crate::variables::Operation;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum NodeTypes {
+    Dialogue,
+    Branches,
+    SetFlag,
+    Conditional, // Branches on a condition, see expression.rs for what can be written in it
+    EmitEvent,
+    Start, // where a conversation begins, games look them up by name
+    End,
+    SetVariable,
+    Random, // picks one of its outputs, see RandomMode
+    Jump,   // goes to another node without a connection being drawn
+    #[default]
+    SomethingHasGoneReallyWrong,
+}
+
+impl NodeTypes {
+    // The "type" of the node in saved files
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeTypes::Dialogue => "dialogue",
+            NodeTypes::Branches => "branches",
+            NodeTypes::Conditional => "conditional",
+            NodeTypes::SetFlag => "set_flag",
+            NodeTypes::EmitEvent => "emit_event",
+            NodeTypes::Start => "start",
+            NodeTypes::End => "end",
+            NodeTypes::SetVariable => "set_variable",
+            NodeTypes::Random => "random",
+            NodeTypes::Jump => "jump",
+            NodeTypes::SomethingHasGoneReallyWrong => "unknown",
+        }
+    }
+}
+
+// How a random node picks, the weights are how often each output comes up in all of them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RandomMode {
+    Weighted, // a new roll every time
+    Shuffle,  // every output comes up weight times before any of them repeats
+    Cycle,    // like shuffle but in order, always the same sequence
+}
+
+impl RandomMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RandomMode::Weighted => "weighted",
+            RandomMode::Shuffle => "shuffle",
+            RandomMode::Cycle => "cycle",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<RandomMode> {
+        match name {
+            "weighted" => Some(RandomMode::Weighted),
+            "shuffle" => Some(RandomMode::Shuffle),
+            "cycle" => Some(RandomMode::Cycle),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> RandomMode {
+        match self {
+            RandomMode::Weighted => RandomMode::Shuffle,
+            RandomMode::Shuffle => RandomMode::Cycle,
+            RandomMode::Cycle => RandomMode::Weighted,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeMember {
+    Character,
+    Dialogue,
+    Branch(usize),
+    BranchCondition(usize),
+    BranchShowDisabled(usize), // not text, clicking it switches between hiding and disabling
+    Condition,
+    FlagToSet,
+    ValueToSet,
+    EventToEmit,
+    EventDataKey(usize),
+    EventDataVal(usize),
+    EntryName,
+    ExitCode,
+    Variable,
+    Operation, // not text, clicking it goes to the next operation
+    VariableValue,
+    Weight(usize),
+    RandomMode, // not text, clicking it goes to the next mode
+    JumpTarget,
+}
+
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct Node {
+    pub id: String,
+    pub character: Option<String>,
+    pub dialogue: Option<String>,
+    pub branches: Option<Vec<String>>,
+    // One per branch, the condition to show it (empty when it's always shown) and whether it's
+    // shown disabled instead of hidden when the condition is false
+    pub branch_conditions: Option<Vec<String>>,
+    pub branch_show_disabled: Option<Vec<bool>>,
+    pub condition: Option<String>, // the source as it was typed
+    pub flag_to_set: Option<String>,
+    pub value_to_set: Option<bool>,
+    pub front_links: Vec<String>, // Vector of other Nodes' ids
+    pub event_to_emit: Option<String>,
+    pub event_data: Option<Vec<(String, String)>>, // TODO?: Maybe integrate the JsonType from my json parser
+    pub entry_name: Option<String>,
+    pub exit_code: Option<i64>, // None for ends that don't need to tell the game anything
+    pub variable: Option<String>,
+    pub operation: Option<Operation>,
+    pub variable_value: Option<String>, // as typed, it's read as the type the variable was declared with
+    pub weights: Option<Vec<i64>>,      // one per output
+    pub random_mode: Option<RandomMode>,
+    // Id of the node a jump goes to, ids stay the same when a start or anything else is renamed
+    pub jump_target: Option<String>,
+    pub node_type: NodeTypes,
+}
+
+impl Node {
+    pub fn default_dialogue() -> Node {
+        Node {
+            character: Some("".to_string()),
+            dialogue: Some("".to_string()),
+            front_links: vec![],
+            node_type: NodeTypes::Dialogue,
+            ..Node::default()
+        }
+    }
+    pub fn new_dialogue<T: ToString>(
+        id: T,
+        character: T,
+        dialogue: T,
+        front_links: Vec<String>,
+    ) -> Node {
+        Node {
+            id: id.to_string(),
+            character: Some(character.to_string()),
+            dialogue: Some(dialogue.to_string()),
+            front_links,
+            ..Node::default_dialogue()
+        }
+    }
+
+    pub fn default_branches() -> Node {
+        Node {
+            branches: Some(vec![]),
+            branch_conditions: Some(vec![]),
+            branch_show_disabled: Some(vec![]),
+            front_links: vec![],
+            node_type: NodeTypes::Branches,
+            ..Node::default()
+        }
+    }
+    pub fn new_branches<T: ToString>(id: T, branches: Vec<String>, front_links: Vec<String>) -> Node {
+        Node {
+            id: id.to_string(),
+            branch_conditions: Some(vec!["".to_string(); branches.len()]),
+            branch_show_disabled: Some(vec![false; branches.len()]),
+            branches: Some(branches),
+            front_links,
+            ..Node::default_branches()
+        }
+    }
+
+    // Keeps everything a branch has in step, new branches are always shown
+    pub fn push_branch(&mut self, text: String) {
+        self.branches.as_mut().unwrap().push(text);
+        self.branch_conditions
+            .as_mut()
+            .unwrap()
+            .push("".to_string());
+        self.branch_show_disabled.as_mut().unwrap().push(false);
+        self.front_links.push("".to_string());
+    }
+
+    pub fn default_conditional() -> Node {
+        Node {
+            condition: Some("".to_string()),
+            front_links: vec!["".to_string(), "".to_string(), "".to_string()],
+            node_type: NodeTypes::Conditional,
+            ..Node::default()
+        }
+    }
+    pub fn new_conditional<T: ToString>(id: T, condition: T, front_links: Vec<String>) -> Node {
+        let mut to_return = Node {
+            id: id.to_string(),
+            condition: Some(condition.to_string()),
+            ..Node::default_conditional()
+        };
+
+        // A true, a false and a not set output, anything else keeps the empty ones
+        debug_assert_eq!(front_links.len(), 3, "a conditional has 3 outputs");
+        if front_links.len() == 3 {
+            to_return.front_links = front_links;
+        }
+
+        to_return
+    }
+
+    pub fn default_set_flag() -> Node {
+        Node {
+            flag_to_set: Some("".to_string()),
+            value_to_set: Some(false),
+            front_links: vec![],
+            node_type: NodeTypes::SetFlag,
+            ..Node::default()
+        }
+    }
+    pub fn new_set_flag<T: ToString>(
+        id: T,
+        flag_to_set: T,
+        value_to_set: bool,
+        front_links: Vec<String>,
+    ) -> Node {
+        Node {
+            id: id.to_string(),
+            flag_to_set: Some(flag_to_set.to_string()),
+            value_to_set: Some(value_to_set),
+            front_links,
+            ..Node::default_set_flag()
+        }
+    }
+
+    pub fn default_emit_event() -> Node {
+        Node {
+            event_to_emit: Some("".to_string()),
+            node_type: NodeTypes::EmitEvent,
+            ..Node::default()
+        }
+    }
+    pub fn new_emit_event<T: ToString>(
+        id: T,
+        event_to_emit: T,
+        event_data: Vec<(String, String)>,
+        front_links: Vec<String>,
+    ) -> Node {
+        Node {
+            id: id.to_string(),
+            event_to_emit: Some(event_to_emit.to_string()),
+            event_data: Some(event_data),
+            front_links,
+            ..Node::default_emit_event()
+        }
+    }
+
+    pub fn default_start() -> Node {
+        Node {
+            entry_name: Some("".to_string()),
+            front_links: vec!["".to_string()],
+            node_type: NodeTypes::Start,
+            ..Node::default()
+        }
+    }
+    pub fn new_start<T: ToString>(id: T, entry_name: T, front_links: Vec<String>) -> Node {
+        Node {
+            id: id.to_string(),
+            entry_name: Some(entry_name.to_string()),
+            front_links,
+            ..Node::default_start()
+        }
+    }
+
+    // Ends have nowhere to go, so no front_links
+    pub fn default_end() -> Node {
+        Node {
+            node_type: NodeTypes::End,
+            ..Node::default()
+        }
+    }
+    pub fn new_end<T: ToString>(id: T, exit_code: Option<i64>) -> Node {
+        Node {
+            id: id.to_string(),
+            exit_code,
+            ..Node::default_end()
+        }
+    }
+
+    pub fn default_set_variable() -> Node {
+        Node {
+            variable: Some("".to_string()),
+            operation: Some(Operation::Assign),
+            variable_value: Some("".to_string()),
+            front_links: vec!["".to_string()],
+            node_type: NodeTypes::SetVariable,
+            ..Node::default()
+        }
+    }
+    pub fn new_set_variable<T: ToString>(
+        id: T,
+        variable: T,
+        operation: Operation,
+        variable_value: T,
+        front_links: Vec<String>,
+    ) -> Node {
+        Node {
+            id: id.to_string(),
+            variable: Some(variable.to_string()),
+            operation: Some(operation),
+            variable_value: Some(variable_value.to_string()),
+            front_links,
+            ..Node::default_set_variable()
+        }
+    }
+
+    pub fn default_random() -> Node {
+        Node {
+            weights: Some(vec![]),
+            random_mode: Some(RandomMode::Weighted),
+            front_links: vec![],
+            node_type: NodeTypes::Random,
+            ..Node::default()
+        }
+    }
+    pub fn new_random<T: ToString>(
+        id: T,
+        weights: Vec<i64>,
+        random_mode: RandomMode,
+        front_links: Vec<String>,
+    ) -> Node {
+        Node {
+            id: id.to_string(),
+            weights: Some(weights),
+            random_mode: Some(random_mode),
+            front_links,
+            ..Node::default_random()
+        }
+    }
+
+    pub fn default_jump() -> Node {
+        Node {
+            jump_target: Some("".to_string()),
+            front_links: vec![],
+            node_type: NodeTypes::Jump,
+            ..Node::default()
+        }
+    }
+    pub fn new_jump<T: ToString>(id: T, jump_target: T) -> Node {
+        Node {
+            id: id.to_string(),
+            jump_target: Some(jump_target.to_string()),
+            ..Node::default_jump()
+        }
+    }
+
+    pub fn push_random_output(&mut self, weight: i64) {
+        self.weights.as_mut().unwrap().push(weight);
+        self.front_links.push("".to_string());
+    }
+
+    // Text of the member a text input is editing. Members that aren't typed in give the same
+    // text the file format uses for them.
+    pub fn get_member_text(&self, member: &NodeMember) -> String {
+        match member {
+            NodeMember::Character => self.character.clone().unwrap(),
+            NodeMember::Dialogue => self.dialogue.clone().unwrap(),
+            NodeMember::Branch(i) => self.branches.clone().unwrap()[*i].clone(),
+            NodeMember::BranchCondition(i) => self.branch_conditions.clone().unwrap()[*i].clone(),
+            NodeMember::Condition => self.condition.clone().unwrap(),
+            NodeMember::FlagToSet => self.flag_to_set.clone().unwrap(),
+            NodeMember::EventToEmit => self.event_to_emit.clone().unwrap(),
+            NodeMember::EventDataKey(i) => self.event_data.clone().unwrap()[*i].0.clone(),
+            NodeMember::EventDataVal(i) => self.event_data.clone().unwrap()[*i].1.clone(),
+            NodeMember::EntryName => self.entry_name.clone().unwrap(),
+            NodeMember::Variable => self.variable.clone().unwrap(),
+            NodeMember::VariableValue => self.variable_value.clone().unwrap(),
+            NodeMember::Weight(i) => self.weights.clone().unwrap()[*i].to_string(),
+            NodeMember::JumpTarget => self.jump_target.clone().unwrap(),
+            NodeMember::ExitCode => match self.exit_code {
+                Some(code) => code.to_string(),
+                None => "".to_string(),
+            },
+            NodeMember::BranchShowDisabled(i) => {
+                self.branch_show_disabled.clone().unwrap()[*i].to_string()
+            }
+            NodeMember::ValueToSet => self.value_to_set.unwrap().to_string(),
+            NodeMember::Operation => self.operation.unwrap().name().to_string(),
+            NodeMember::RandomMode => self.random_mode.unwrap().name().to_string(),
+        }
+    }
+
+    pub fn set_member_text(&mut self, member: &NodeMember, new_text: String) {
+        match member {
+            NodeMember::Character => self.character = Some(new_text),
+            NodeMember::Dialogue => self.dialogue = Some(new_text),
+            NodeMember::Branch(i) => self.branches.as_mut().unwrap()[*i] = new_text,
+            NodeMember::BranchCondition(i) => {
+                self.branch_conditions.as_mut().unwrap()[*i] = new_text
+            }
+            NodeMember::Condition => self.condition = Some(new_text),
+            NodeMember::FlagToSet => self.flag_to_set = Some(new_text),
+            NodeMember::EventToEmit => self.event_to_emit = Some(new_text),
+            NodeMember::EventDataKey(i) => self.event_data.as_mut().unwrap()[*i].0 = new_text,
+            NodeMember::EventDataVal(i) => self.event_data.as_mut().unwrap()[*i].1 = new_text,
+            NodeMember::EntryName => self.entry_name = Some(new_text),
+            NodeMember::Variable => self.variable = Some(new_text),
+            NodeMember::VariableValue => self.variable_value = Some(new_text),
+            NodeMember::JumpTarget => self.jump_target = Some(new_text),
+            // Anything that isn't a whole number leaves the end without a code
+            NodeMember::ExitCode => self.exit_code = new_text.trim().parse().ok(),
+            // Stays at the last valid weight while the text isn't one
+            NodeMember::Weight(i) => {
+                if let Ok(weight) = new_text.trim().parse() {
+                    self.weights.as_mut().unwrap()[*i] = weight;
+                }
+            }
+            // Same for the rest, they only change when the text is one of their values
+            NodeMember::BranchShowDisabled(i) => {
+                if let Ok(show_disabled) = new_text.trim().parse() {
+                    self.branch_show_disabled.as_mut().unwrap()[*i] = show_disabled;
+                }
+            }
+            NodeMember::ValueToSet => {
+                if let Ok(value) = new_text.trim().parse() {
+                    self.value_to_set = Some(value);
+                }
+            }
+            NodeMember::Operation => {
+                if let Some(operation) = Operation::from_name(new_text.trim()) {
+                    self.operation = Some(operation);
+                }
+            }
+            NodeMember::RandomMode => {
+                if let Some(random_mode) = RandomMode::from_name(new_text.trim()) {
+                    self.random_mode = Some(random_mode);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pushed_branches_and_outputs_stay_in_step() {
+        let mut branches = Node::default_branches();
+        branches.push_branch("Yes".to_string());
+        branches.push_branch("No".to_string());
+        assert_eq!(
+            branches.branches,
+            Some(vec!["Yes".to_string(), "No".to_string()])
+        );
+        assert_eq!(branches.branch_conditions, Some(vec!["".to_string(); 2]));
+        assert_eq!(branches.branch_show_disabled, Some(vec![false; 2]));
+        assert_eq!(branches.front_links, vec!["".to_string(); 2]);
+
+        let mut random = Node::default_random();
+        random.push_random_output(3);
+        assert_eq!(random.weights, Some(vec![3]));
+        assert_eq!(random.front_links, vec!["".to_string()]);
+    }
+
+    #[test]
+    fn member_text_goes_back_and_forth() {
+        let mut node = Node::default_random();
+        node.push_random_output(1);
+        node.set_member_text(&NodeMember::Weight(0), " 4 ".to_string());
+        node.set_member_text(&NodeMember::RandomMode, "cycle".to_string());
+        assert_eq!(node.get_member_text(&NodeMember::Weight(0)), "4");
+        assert_eq!(node.get_member_text(&NodeMember::RandomMode), "cycle");
+
+        // Text that isn't a value keeps the last one
+        node.set_member_text(&NodeMember::Weight(0), "4.5".to_string());
+        node.set_member_text(&NodeMember::RandomMode, "sometimes".to_string());
+        assert_eq!(node.weights, Some(vec![4]));
+        assert_eq!(node.random_mode, Some(RandomMode::Cycle));
+
+        // Except exit codes, which can be left out
+        let mut end = Node::new_end("00001", Some(2));
+        end.set_member_text(&NodeMember::ExitCode, "".to_string());
+        assert_eq!(end.exit_code, None);
+        assert_eq!(end.get_member_text(&NodeMember::ExitCode), "");
+    }
+
+    #[test]
+    fn random_modes_go_around_by_name() {
+        let mut mode = RandomMode::Weighted;
+        for _ in 0..3 {
+            assert_eq!(RandomMode::from_name(mode.name()), Some(mode));
+            mode = mode.next();
+        }
+        assert_eq!(mode, RandomMode::Weighted);
+        assert_eq!(RandomMode::from_name("Weighted"), None);
+    }
+}
@@ -0,0 +1,443 @@
+// The text being typed in the editor's text input. The caret and the selection anchor are byte
+// indexes into the text and are always kept on char boundaries, so any UTF-8 text works.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextEdit {
+    text: String,
+    caret: usize,
+    anchor: Option<usize>, // the other end of the selection, if there is one
+}
+
+#[derive(PartialEq)]
+enum CharClass {
+    Space,
+    Word,
+    Other,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        return CharClass::Space;
+    }
+    if c.is_alphanumeric() || c == '_' {
+        return CharClass::Word;
+    }
+    CharClass::Other
+}
+
+impl TextEdit {
+    // Starts with the caret at the end
+    pub fn new(text: &str) -> TextEdit {
+        TextEdit {
+            text: text.to_string(),
+            caret: text.len(),
+            anchor: None,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    // Start and end of the selection, in that order
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        match self.anchor {
+            Some(anchor) if anchor != self.caret => {
+                Some((anchor.min(self.caret), anchor.max(self.caret)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        let (start, end) = self.selection()?;
+        Some(&self.text[start..end])
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.caret = self.text.len();
+    }
+
+    pub fn insert_str(&mut self, s: &str) {
+        self.delete_selection();
+        self.text.insert_str(self.caret, s);
+        self.caret += s.len();
+    }
+
+    // Control characters are ignored, newlines have to go through insert_str
+    pub fn insert_char(&mut self, c: char) {
+        if c.is_control() {
+            return;
+        }
+        let mut buf = [0; 4];
+        self.insert_str(c.encode_utf8(&mut buf));
+    }
+
+    // Returns the removed text, for cut
+    pub fn delete_selection(&mut self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        let to_return = self.text[start..end].to_string();
+        self.text.replace_range(start..end, "");
+        self.caret = start;
+        self.anchor = None;
+        Some(to_return)
+    }
+
+    pub fn backspace(&mut self, whole_word: bool) {
+        if self.delete_selection().is_some() {
+            return;
+        }
+        let start = if whole_word {
+            self.word_start_before(self.caret)
+        } else {
+            self.prev_boundary(self.caret)
+        };
+        self.text.replace_range(start..self.caret, "");
+        self.caret = start;
+    }
+
+    pub fn delete(&mut self, whole_word: bool) {
+        if self.delete_selection().is_some() {
+            return;
+        }
+        let end = if whole_word {
+            self.word_end_after(self.caret)
+        } else {
+            self.next_boundary(self.caret)
+        };
+        self.text.replace_range(self.caret..end, "");
+    }
+
+    pub fn move_left(&mut self, whole_word: bool, select: bool) {
+        // Like most text fields, left with a selection just goes to its start
+        match self.selection() {
+            Some((start, _)) if !select => {
+                self.move_to(start, false);
+                return;
+            }
+            _ => {}
+        }
+        let to = if whole_word {
+            self.word_start_before(self.caret)
+        } else {
+            self.prev_boundary(self.caret)
+        };
+        self.move_to(to, select);
+    }
+
+    pub fn move_right(&mut self, whole_word: bool, select: bool) {
+        match self.selection() {
+            Some((_, end)) if !select => {
+                self.move_to(end, false);
+                return;
+            }
+            _ => {}
+        }
+        let to = if whole_word {
+            self.word_end_after(self.caret)
+        } else {
+            self.next_boundary(self.caret)
+        };
+        self.move_to(to, select);
+    }
+
+    pub fn home(&mut self, select: bool) {
+        self.move_to(0, select);
+    }
+
+    pub fn end(&mut self, select: bool) {
+        self.move_to(self.text.len(), select);
+    }
+
+    // Used for moves that depend on how the text is drawn, like going a line up
+    pub fn move_to(&mut self, to: usize, select: bool) {
+        if select {
+            if self.anchor.is_none() {
+                self.anchor = Some(self.caret);
+            }
+        } else {
+            self.anchor = None;
+        }
+        self.caret = to;
+    }
+
+    fn prev_boundary(&self, i: usize) -> usize {
+        match self.text[..i].char_indices().next_back() {
+            Some((found, _)) => found,
+            None => 0,
+        }
+    }
+
+    fn next_boundary(&self, i: usize) -> usize {
+        match self.text[i..].chars().next() {
+            Some(c) => i + c.len_utf8(),
+            None => i,
+        }
+    }
+
+    // Skips the spaces right before i and then everything of the same kind as the char before them
+    fn word_start_before(&self, i: usize) -> usize {
+        let mut chars = self.text[..i].char_indices().rev().peekable();
+        let mut to_return = i;
+
+        while let Some((found, c)) = chars.peek() {
+            if char_class(*c) != CharClass::Space {
+                break;
+            }
+            to_return = *found;
+            chars.next();
+        }
+
+        let class = match chars.peek() {
+            Some((_, c)) => char_class(*c),
+            None => return to_return,
+        };
+        for (found, c) in chars {
+            if char_class(c) != class {
+                break;
+            }
+            to_return = found;
+        }
+
+        to_return
+    }
+
+    fn word_end_after(&self, i: usize) -> usize {
+        let mut chars = self.text[i..].char_indices().peekable();
+        let mut to_return = i;
+
+        while let Some((found, c)) = chars.peek() {
+            if char_class(*c) != CharClass::Space {
+                break;
+            }
+            to_return = i + found + c.len_utf8();
+            chars.next();
+        }
+
+        let class = match chars.peek() {
+            Some((_, c)) => char_class(*c),
+            None => return to_return,
+        };
+        for (found, c) in chars {
+            if char_class(c) != class {
+                break;
+            }
+            to_return = i + found + c.len_utf8();
+        }
+
+        to_return
+    }
+}
+
+// Splits text into lines no wider than max_width, breaking at spaces when it can and inside a word
+// only when the word alone doesn't fit. Gives the byte range of each line, the newline or space a
+// line was broken at is left out.
+pub fn wrap_text(text: &str, max_width: i32, measure: impl Fn(&str) -> i32) -> Vec<(usize, usize)> {
+    let mut to_return = vec![];
+
+    let mut paragraph_start = 0;
+    for paragraph in text.split('\n') {
+        let mut line_start = paragraph_start;
+        let mut last_space = None;
+
+        for (i, c) in paragraph.char_indices() {
+            let i = paragraph_start + i;
+            // Spaces can hang past the edge, they are where lines get broken anyway
+            if c == ' ' {
+                last_space = Some(i);
+                continue;
+            }
+
+            while i > line_start && measure(&text[line_start..i + c.len_utf8()]) > max_width {
+                match last_space {
+                    Some(space) if space > line_start => {
+                        to_return.push((line_start, space));
+                        line_start = space + 1;
+                    }
+                    _ => {
+                        to_return.push((line_start, i));
+                        line_start = i;
+                    }
+                }
+                last_space = None;
+            }
+        }
+
+        to_return.push((line_start, paragraph_start + paragraph.len()));
+        paragraph_start += paragraph.len() + 1;
+    }
+
+    to_return
+}
+
+// Index of the line the byte index i is in
+pub fn line_of(lines: &[(usize, usize)], i: usize) -> usize {
+    lines
+        .iter()
+        .rposition(|line| line.0 <= i)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every char is as wide as the next, like a monospace font
+    fn wrap(text: &str, max_width: i32) -> Vec<&str> {
+        wrap_text(text, max_width, |s| s.chars().count() as i32)
+            .iter()
+            .map(|(start, end)| &text[*start..*end])
+            .collect()
+    }
+
+    // The text with the caret as | and the selection in []
+    fn shown(edit: &TextEdit) -> String {
+        let mut to_return = edit.text().to_string();
+        match edit.selection() {
+            Some((start, end)) => {
+                to_return.insert(end, ']');
+                to_return.insert(start, '[');
+            }
+            None => to_return.insert(edit.caret(), '|'),
+        }
+        to_return
+    }
+
+    #[test]
+    fn typing_and_deleting_around_multi_byte_chars() {
+        let mut edit = TextEdit::new("é😀");
+        edit.backspace(false);
+        assert_eq!(shown(&edit), "é|");
+        edit.insert_char('ツ');
+        edit.insert_char('\u{7}');
+        edit.move_left(false, false);
+        edit.move_left(false, false);
+        assert_eq!(shown(&edit), "|éツ");
+        edit.insert_str("a\nb");
+        assert_eq!(shown(&edit), "a\nb|éツ");
+        edit.delete(false);
+        assert_eq!(shown(&edit), "a\nb|ツ");
+        edit.end(false);
+        edit.delete(false);
+        edit.backspace(false);
+        edit.backspace(false);
+        assert_eq!(shown(&edit), "a\n|");
+        edit.home(false);
+        edit.backspace(false);
+        assert_eq!(shown(&edit), "|a\n");
+    }
+
+    #[test]
+    fn typing_replaces_the_selection() {
+        let mut edit = TextEdit::new("the ツ cat");
+        edit.home(false);
+        for _ in 0..5 {
+            edit.move_right(false, false);
+        }
+        edit.move_right(false, true);
+        edit.move_right(true, true);
+        assert_eq!(shown(&edit), "the ツ[ cat]");
+        assert_eq!(edit.selected_text(), Some(" cat"));
+
+        edit.insert_char('!');
+        assert_eq!(shown(&edit), "the ツ!|");
+
+        edit.select_all();
+        assert_eq!(edit.delete_selection(), Some("the ツ!".to_string()));
+        assert_eq!(shown(&edit), "|");
+        assert_eq!(edit.delete_selection(), None);
+
+        // Selecting backwards works the same, and moving without shift leaves at that end
+        let mut edit = TextEdit::new("abcd");
+        edit.move_left(false, true);
+        edit.move_left(false, true);
+        assert_eq!(shown(&edit), "ab[cd]");
+        edit.move_left(false, false);
+        assert_eq!(shown(&edit), "ab|cd");
+        edit.move_right(false, true);
+        edit.move_right(false, false);
+        assert_eq!(shown(&edit), "abc|d");
+        edit.move_left(false, true);
+        edit.backspace(false);
+        assert_eq!(shown(&edit), "ab|d");
+    }
+
+    #[test]
+    fn words_are_letters_or_punctuation_runs() {
+        let mut edit = TextEdit::new("say_hi  ツツ, ok");
+        let mut stops = vec![];
+        while edit.caret() > 0 {
+            edit.move_left(true, false);
+            stops.push(shown(&edit));
+        }
+        assert_eq!(
+            stops,
+            [
+                "say_hi  ツツ, |ok",
+                "say_hi  ツツ|, ok",
+                "say_hi  |ツツ, ok",
+                "|say_hi  ツツ, ok"
+            ]
+        );
+
+        let mut stops = vec![];
+        while edit.caret() < edit.text().len() {
+            edit.move_right(true, false);
+            stops.push(shown(&edit));
+        }
+        assert_eq!(
+            stops,
+            [
+                "say_hi|  ツツ, ok",
+                "say_hi  ツツ|, ok",
+                "say_hi  ツツ,| ok",
+                "say_hi  ツツ, ok|"
+            ]
+        );
+
+        edit.backspace(true);
+        assert_eq!(shown(&edit), "say_hi  ツツ, |");
+        edit.backspace(true);
+        assert_eq!(shown(&edit), "say_hi  ツツ|");
+        edit.home(false);
+        edit.delete(true);
+        assert_eq!(shown(&edit), "|  ツツ");
+        edit.delete(true);
+        assert_eq!(shown(&edit), "|");
+    }
+
+    #[test]
+    fn wraps_at_spaces_and_inside_words_that_dont_fit() {
+        assert_eq!(wrap("hello world", 5), ["hello", "world"]);
+        assert_eq!(wrap("hello world", 11), ["hello world"]);
+        assert_eq!(wrap("a bb ccc", 4), ["a bb", "ccc"]);
+        assert_eq!(wrap("abcdefgh", 3), ["abc", "def", "gh"]);
+        assert_eq!(wrap("ツツツツツ", 2), ["ツツ", "ツツ", "ツ"]);
+        // Spaces hang past the edge instead of starting the next line
+        assert_eq!(wrap("ab   cd", 2), ["ab  ", "cd"]);
+        assert_eq!(wrap("ab\n\ncd ef", 2), ["ab", "", "cd", "ef"]);
+        assert_eq!(wrap("", 5), [""]);
+    }
+
+    #[test]
+    fn carets_at_a_break_go_on_the_line_they_begin() {
+        let text = "hello world";
+        let lines = wrap_text(text, 5, |s| s.chars().count() as i32);
+        assert_eq!(lines, [(0, 5), (6, 11)]);
+        assert_eq!(line_of(&lines, 0), 0);
+        assert_eq!(line_of(&lines, 5), 0);
+        assert_eq!(line_of(&lines, 6), 1);
+        assert_eq!(line_of(&lines, 11), 1);
+
+        // Broken inside a word there is no space between, the end of one is the start of the next
+        let lines = wrap_text("ツツツ", 2, |s| s.chars().count() as i32);
+        assert_eq!(lines, [(0, 6), (6, 9)]);
+        assert_eq!(line_of(&lines, 3), 0);
+        assert_eq!(line_of(&lines, 6), 1);
+        assert_eq!(line_of(&lines, 9), 1);
+    }
+}
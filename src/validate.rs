@@ -0,0 +1,685 @@
+// Checks a project for the mistakes that would only show up once it's running in a game
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::expression;
+use crate::node::{Node, NodeTypes};
+use crate::variables::{Value, Variable};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning, // works, but probably not what was meant
+    Error,   // will break or get stuck when it's run
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    pub severity: Severity,
+    pub node_id: String,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(
+            f,
+            "{} in node '{}': {}",
+            severity, self.node_id, self.message
+        )
+    }
+}
+
+// Errors come first, then everything is in node order
+pub fn validate(nodes: &[Node], variables: &[Variable]) -> Vec<Problem> {
+    let mut to_return = vec![];
+    let ids: HashSet<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+    let by_id: HashMap<&str, &Node> = nodes.iter().map(|n| (n.id.as_str(), n)).collect();
+
+    let flags_set: HashSet<&str> = nodes
+        .iter()
+        .filter_map(|n| match n.node_type {
+            NodeTypes::SetFlag => n.flag_to_set.as_deref(),
+            _ => None,
+        })
+        .collect();
+
+    let mut entry_names: HashMap<&str, usize> = HashMap::new();
+    for n in nodes {
+        if let (NodeTypes::Start, Some(name)) = (&n.node_type, &n.entry_name) {
+            *entry_names.entry(name.trim()).or_default() += 1;
+        }
+    }
+
+    for n in nodes {
+        let mut problem = |severity, message: String| {
+            to_return.push(Problem {
+                severity,
+                node_id: n.id.clone(),
+                message,
+            })
+        };
+
+        // Only flags can be unset, without any the 'if not set' output is never taken
+        let never_unset = n.node_type == NodeTypes::Conditional
+            && match expression::parse(n.condition.as_deref().unwrap_or_default()) {
+                Ok(condition) => condition.flags(variables).is_empty(),
+                Err(_) => true, // already an error below
+            };
+
+        for (i, link) in n.front_links.iter().enumerate() {
+            if link.is_empty() && never_unset && i == 2 {
+                continue;
+            }
+            if link.is_empty() {
+                problem(
+                    Severity::Warning,
+                    format!(
+                        "{} is not connected, the conversation ends there",
+                        output_name(n, i)
+                    ),
+                );
+            } else if !ids.contains(link.as_str()) {
+                problem(
+                    Severity::Error,
+                    format!(
+                        "{} goes to node '{}', which doesn't exist",
+                        output_name(n, i),
+                        link
+                    ),
+                );
+            }
+        }
+
+        match n.node_type {
+            NodeTypes::Dialogue => {
+                if is_blank(&n.character) {
+                    problem(Severity::Warning, "character is empty".to_string());
+                }
+                if is_blank(&n.dialogue) {
+                    problem(Severity::Error, "dialogue is empty".to_string());
+                }
+            }
+            NodeTypes::Branches => {
+                let branches = n.branches.clone().unwrap_or_default();
+                if branches.is_empty() {
+                    problem(Severity::Error, "there are no options to pick".to_string());
+                }
+
+                let mut seen = HashSet::new();
+                for branch in &branches {
+                    if !seen.insert(branch) {
+                        problem(
+                            Severity::Warning,
+                            format!("there is more than one option called '{}'", branch),
+                        );
+                    }
+                }
+
+                let conditions = n.branch_conditions.clone().unwrap_or_default();
+                let show_disabled = n.branch_show_disabled.clone().unwrap_or_default();
+                for (branch, source) in branches.iter().zip(&conditions) {
+                    if source.trim().is_empty() {
+                        continue;
+                    }
+                    match expression::check_condition(source, variables) {
+                        Ok(condition) => {
+                            for flag in condition.flags(variables) {
+                                if !flags_set.contains(flag) {
+                                    problem(
+                                        Severity::Warning,
+                                        format!(
+                                            "option '{}': flag '{}' is never set by any set flag node",
+                                            branch, flag
+                                        ),
+                                    );
+                                }
+                            }
+                        }
+                        Err(reason) => {
+                            problem(Severity::Error, format!("option '{}': {}", branch, reason))
+                        }
+                    }
+                }
+
+                let can_hide_all = conditions
+                    .iter()
+                    .zip(&show_disabled)
+                    .all(|(condition, disabled)| !condition.trim().is_empty() && !disabled);
+                if !branches.is_empty() && can_hide_all {
+                    problem(
+                        Severity::Warning,
+                        "every option can be hidden, there may be nothing to pick".to_string(),
+                    );
+                }
+            }
+            NodeTypes::Conditional => {
+                let source = n.condition.clone().unwrap_or_default();
+                match expression::check_condition(&source, variables) {
+                    Ok(condition) => {
+                        for flag in condition.flags(variables) {
+                            if !flags_set.contains(flag) {
+                                problem(
+                                    Severity::Warning,
+                                    format!("flag '{}' is never set by any set flag node", flag),
+                                );
+                            }
+                        }
+                    }
+                    Err(reason) => problem(Severity::Error, reason),
+                }
+            }
+            NodeTypes::SetFlag if is_blank(&n.flag_to_set) => {
+                problem(Severity::Error, "the flag to set is empty".to_string());
+            }
+            NodeTypes::EmitEvent if is_blank(&n.event_to_emit) => {
+                problem(Severity::Error, "the event name is empty".to_string());
+            }
+            NodeTypes::SetVariable => {
+                let name = n.variable.clone().unwrap_or_default();
+                let declared = variables.iter().find(|v| v.name == name.trim());
+                match declared {
+                    _ if name.trim().is_empty() => {
+                        problem(Severity::Error, "the variable to set is empty".to_string());
+                    }
+                    None => problem(
+                        Severity::Error,
+                        format!("variable '{}' is not declared", name.trim()),
+                    ),
+                    Some(declared) => {
+                        let text = n.variable_value.clone().unwrap_or_default();
+                        let result =
+                            Value::parse(&text, declared.default.var_type()).and_then(|value| {
+                                n.operation.unwrap().apply(&declared.default, &value)
+                            });
+                        if let Err(reason) = result {
+                            problem(Severity::Error, format!("'{}': {}", name.trim(), reason));
+                        }
+                    }
+                }
+            }
+            NodeTypes::Random => {
+                let weights = n.weights.clone().unwrap_or_default();
+                if weights.is_empty() {
+                    problem(Severity::Error, "there are no outputs to pick".to_string());
+                } else if weights.iter().any(|w| *w < 0) {
+                    problem(Severity::Error, "weights can't be negative".to_string());
+                } else if weights.iter().all(|w| *w == 0) {
+                    problem(
+                        Severity::Error,
+                        "every weight is 0, there is nothing to pick".to_string(),
+                    );
+                }
+            }
+            NodeTypes::Jump => {
+                let target = n.jump_target.clone().unwrap_or_default();
+                if target.is_empty() {
+                    problem(Severity::Error, "the jump has no target".to_string());
+                } else if !ids.contains(target.as_str()) {
+                    problem(
+                        Severity::Error,
+                        format!("jumps to node '{}', which doesn't exist", target),
+                    );
+                } else if jumps_forever(&by_id, n) {
+                    problem(
+                        Severity::Error,
+                        "jumps go around in a loop, it would never end".to_string(),
+                    );
+                }
+            }
+            NodeTypes::Start => {
+                let name = n.entry_name.clone().unwrap_or_default();
+                if name.trim().is_empty() {
+                    problem(Severity::Error, "the start has no name".to_string());
+                } else if entry_names[name.trim()] > 1 {
+                    problem(
+                        Severity::Error,
+                        format!("there is more than one start called '{}'", name.trim()),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for id in unreachable_nodes(nodes) {
+        to_return.push(Problem {
+            severity: Severity::Warning,
+            node_id: id,
+            message: "can't be reached from any start".to_string(),
+        });
+    }
+
+    let order: HashMap<&str, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (n.id.as_str(), i))
+        .collect();
+    to_return.sort_by_key(|p| (std::cmp::Reverse(p.severity), order[p.node_id.as_str()]));
+
+    to_return
+}
+
+// Conversations begin at the start nodes. Projects from before there were any begin wherever the
+// game wants, so there every node nothing links to counts as a start.
+fn unreachable_nodes(nodes: &[Node]) -> Vec<String> {
+    let next_of: HashMap<&str, Vec<&str>> =
+        nodes.iter().map(|n| (n.id.as_str(), links(n))).collect();
+
+    let mut to_visit: Vec<&str> = nodes
+        .iter()
+        .filter(|n| n.node_type == NodeTypes::Start)
+        .map(|n| n.id.as_str())
+        .collect();
+    if to_visit.is_empty() {
+        let linked_to: HashSet<&str> = nodes.iter().flat_map(links).collect();
+        to_visit = nodes
+            .iter()
+            .map(|n| n.id.as_str())
+            .filter(|id| !linked_to.contains(id))
+            .collect();
+    }
+
+    let mut reached = HashSet::new();
+
+    while let Some(id) = to_visit.pop() {
+        if !reached.insert(id) {
+            continue;
+        }
+        if let Some(next) = next_of.get(id) {
+            to_visit.extend(next);
+        }
+    }
+
+    nodes
+        .iter()
+        .filter(|n| !reached.contains(n.id.as_str()))
+        .map(|n| n.id.clone())
+        .collect()
+}
+
+// Where a node can go next, outputs and jumps alike
+fn links(n: &Node) -> Vec<&str> {
+    n.front_links
+        .iter()
+        .chain(n.jump_target.iter())
+        .map(|l| l.as_str())
+        .collect()
+}
+
+// Jumps that only lead to more jumps, back to one that was already passed
+fn jumps_forever(by_id: &HashMap<&str, &Node>, jump: &Node) -> bool {
+    let mut passed = HashSet::new();
+    let mut cur = jump;
+    while cur.node_type == NodeTypes::Jump {
+        if !passed.insert(cur.id.as_str()) {
+            return true;
+        }
+        let target = cur.jump_target.as_deref().unwrap_or_default();
+        cur = match by_id.get(target) {
+            Some(next) => next,
+            None => return false,
+        };
+    }
+    false
+}
+
+fn output_name(n: &Node, i: usize) -> String {
+    match n.node_type {
+        NodeTypes::Branches => match n.branches.as_ref().and_then(|b| b.get(i)) {
+            Some(branch) => format!("option '{}'", branch),
+            None => format!("output {}", i + 1),
+        },
+        NodeTypes::Random => format!("output {}", i + 1),
+        NodeTypes::Conditional => match i {
+            0 => "the 'if true' output".to_string(),
+            1 => "the 'if false' output".to_string(),
+            _ => "the 'if not set' output".to_string(),
+        },
+        _ => "the output".to_string(),
+    }
+}
+
+fn is_blank(text: &Option<String>) -> bool {
+    match text {
+        Some(text) => text.trim().is_empty(),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::RandomMode;
+    use crate::variables::Operation;
+
+    fn links(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    // The node being checked is 00002, between a start and an end
+    fn around(node: Node) -> Vec<Node> {
+        vec![
+            Node::new_start("00001", "intro", links(&["00002"])),
+            node,
+            Node::new_end("00003", None),
+        ]
+    }
+
+    fn problems(nodes: &[Node]) -> Vec<String> {
+        let variables = vec![Variable {
+            name: "gold".to_string(),
+            default: Value::Int(5),
+        }];
+        validate(nodes, &variables)
+            .iter()
+            .map(|p| p.to_string())
+            .collect()
+    }
+
+    fn line(character: &str, dialogue: &str) -> Node {
+        Node::new_dialogue("00002", character, dialogue, links(&["00003"]))
+    }
+
+    fn options(texts: &[&str], conditions: &[&str], show_disabled: &[bool]) -> Node {
+        let mut to_return = Node::new_branches(
+            "00002",
+            links(texts),
+            vec!["00003".to_string(); texts.len()],
+        );
+        to_return.branch_conditions = Some(links(conditions));
+        to_return.branch_show_disabled = Some(show_disabled.to_vec());
+        to_return
+    }
+
+    fn conditional(condition: &str) -> Node {
+        Node::new_conditional("00002", condition, links(&["00003", "00003", "00003"]))
+    }
+
+    fn set_variable(variable: &str, operation: Operation, value: &str) -> Node {
+        Node::new_set_variable("00002", variable, operation, value, links(&["00003"]))
+    }
+
+    fn random(weights: Vec<i64>) -> Node {
+        let outputs = vec!["00003".to_string(); weights.len()];
+        Node::new_random("00002", weights, RandomMode::Weighted, outputs)
+    }
+
+    #[test]
+    fn a_finished_project_has_no_problems() {
+        assert_eq!(problems(&around(line("Bob", "Hi"))), Vec::<String>::new());
+        let mut nodes = around(conditional("gold > 1"));
+        // Conditions without flags are never unset, that output can stay empty
+        nodes[1].front_links[2] = "".to_string();
+        assert_eq!(problems(&nodes), Vec::<String>::new());
+    }
+
+    #[test]
+    fn unconnected_outputs_end_the_conversation() {
+        let mut nodes = around(options(&["Yes", "No"], &["", ""], &[false, false]));
+        nodes[1].front_links[1] = "".to_string();
+        assert_eq!(
+            problems(&nodes),
+            ["warning in node '00002': option 'No' is not connected, the conversation ends there"]
+        );
+    }
+
+    #[test]
+    fn outputs_to_missing_nodes() {
+        let mut nodes = around(conditional("gold > 1"));
+        nodes[1].front_links[1] = "00009".to_string();
+        assert_eq!(
+            problems(&nodes),
+            ["error in node '00002': the 'if false' output goes to node '00009', which doesn't exist"]
+        );
+    }
+
+    #[test]
+    fn empty_characters_and_dialogue() {
+        assert_eq!(
+            problems(&around(line(" ", "Hi"))),
+            ["warning in node '00002': character is empty"]
+        );
+        assert_eq!(
+            problems(&around(line("Bob", ""))),
+            ["error in node '00002': dialogue is empty"]
+        );
+    }
+
+    #[test]
+    fn branches_without_options() {
+        assert_eq!(
+            problems(&around(options(&[], &[], &[]))),
+            [
+                "error in node '00002': there are no options to pick",
+                "warning in node '00003': can't be reached from any start"
+            ]
+        );
+    }
+
+    #[test]
+    fn options_with_the_same_text() {
+        assert_eq!(
+            problems(&around(options(
+                &["Yes", "No", "Yes"],
+                &["", "", ""],
+                &[false; 3]
+            ))),
+            ["warning in node '00002': there is more than one option called 'Yes'"]
+        );
+    }
+
+    #[test]
+    fn options_checking_flags_nothing_sets() {
+        assert_eq!(
+            problems(&around(options(&["Yes", "No"], &["met", ""], &[false, false]))),
+            ["warning in node '00002': option 'Yes': flag 'met' is never set by any set flag node"]
+        );
+    }
+
+    #[test]
+    fn options_with_broken_conditions() {
+        assert_eq!(
+            problems(&around(options(
+                &["Yes", "No"],
+                &["", "gold >"],
+                &[false, false]
+            ))),
+            ["error in node '00002': option 'No': the condition ends too soon"]
+        );
+    }
+
+    #[test]
+    fn options_that_can_all_be_hidden() {
+        let nodes = around(options(
+            &["Yes", "No"],
+            &["gold > 1", "gold < 1"],
+            &[false, false],
+        ));
+        assert_eq!(
+            problems(&nodes),
+            ["warning in node '00002': every option can be hidden, there may be nothing to pick"]
+        );
+        let nodes = around(options(
+            &["Yes", "No"],
+            &["gold > 1", "gold < 1"],
+            &[false, true],
+        ));
+        assert_eq!(problems(&nodes), Vec::<String>::new());
+    }
+
+    #[test]
+    fn conditionals_checking_flags_nothing_sets() {
+        let mut nodes = around(conditional("met or gold > 1"));
+        assert_eq!(
+            problems(&nodes),
+            ["warning in node '00002': flag 'met' is never set by any set flag node"]
+        );
+        nodes[0].front_links = links(&["00004"]);
+        nodes.push(Node::new_set_flag("00004", "met", true, links(&["00002"])));
+        assert_eq!(problems(&nodes), Vec::<String>::new());
+    }
+
+    #[test]
+    fn conditionals_with_broken_conditions() {
+        assert_eq!(
+            problems(&around(conditional("gold > \"a\""))),
+            ["error in node '00002': can't compare a int with a string"]
+        );
+    }
+
+    #[test]
+    fn empty_flags_and_events() {
+        let node = Node::new_set_flag("00002", " ", true, links(&["00003"]));
+        assert_eq!(
+            problems(&around(node)),
+            ["error in node '00002': the flag to set is empty"]
+        );
+        let node = Node::new_emit_event("00002", "", vec![], links(&["00003"]));
+        assert_eq!(
+            problems(&around(node)),
+            ["error in node '00002': the event name is empty"]
+        );
+    }
+
+    #[test]
+    fn setting_no_variable() {
+        assert_eq!(
+            problems(&around(set_variable(" ", Operation::Assign, "1"))),
+            ["error in node '00002': the variable to set is empty"]
+        );
+    }
+
+    #[test]
+    fn setting_undeclared_variables() {
+        assert_eq!(
+            problems(&around(set_variable("silver", Operation::Assign, "1"))),
+            ["error in node '00002': variable 'silver' is not declared"]
+        );
+    }
+
+    #[test]
+    fn setting_values_of_the_wrong_type() {
+        assert_eq!(
+            problems(&around(set_variable("gold", Operation::Add, "a lot"))),
+            ["error in node '00002': 'gold': 'a lot' is not a whole number"]
+        );
+    }
+
+    #[test]
+    fn random_nodes_without_anything_to_pick() {
+        assert_eq!(
+            problems(&around(random(vec![]))),
+            [
+                "error in node '00002': there are no outputs to pick",
+                "warning in node '00003': can't be reached from any start"
+            ]
+        );
+        assert_eq!(
+            problems(&around(random(vec![0, 0]))),
+            ["error in node '00002': every weight is 0, there is nothing to pick"]
+        );
+    }
+
+    #[test]
+    fn negative_weights() {
+        assert_eq!(
+            problems(&around(random(vec![1, -1]))),
+            ["error in node '00002': weights can't be negative"]
+        );
+    }
+
+    #[test]
+    fn jumps_without_a_target() {
+        assert_eq!(
+            problems(&around(Node::new_jump("00002", ""))),
+            [
+                "error in node '00002': the jump has no target",
+                "warning in node '00003': can't be reached from any start"
+            ]
+        );
+    }
+
+    #[test]
+    fn jumps_to_missing_nodes() {
+        assert_eq!(
+            problems(&around(Node::new_jump("00002", "00009"))),
+            [
+                "error in node '00002': jumps to node '00009', which doesn't exist",
+                "warning in node '00003': can't be reached from any start"
+            ]
+        );
+    }
+
+    #[test]
+    fn jumps_in_a_loop() {
+        let nodes = vec![
+            Node::new_start("00001", "intro", links(&["00002"])),
+            Node::new_jump("00002", "00003"),
+            Node::new_jump("00003", "00002"),
+        ];
+        assert_eq!(
+            problems(&nodes),
+            [
+                "error in node '00002': jumps go around in a loop, it would never end",
+                "error in node '00003': jumps go around in a loop, it would never end"
+            ]
+        );
+    }
+
+    #[test]
+    fn starts_without_a_name() {
+        let mut nodes = around(line("Bob", "Hi"));
+        nodes[0].entry_name = Some(" ".to_string());
+        assert_eq!(
+            problems(&nodes),
+            ["error in node '00001': the start has no name"]
+        );
+    }
+
+    #[test]
+    fn starts_with_the_same_name() {
+        let mut nodes = around(line("Bob", "Hi"));
+        nodes.push(Node::new_start("00004", "intro ", links(&["00003"])));
+        assert_eq!(
+            problems(&nodes),
+            [
+                "error in node '00001': there is more than one start called 'intro'",
+                "error in node '00004': there is more than one start called 'intro'"
+            ]
+        );
+    }
+
+    #[test]
+    fn nodes_no_start_reaches() {
+        let mut nodes = around(line("Bob", "Hi"));
+        nodes.push(Node::new_dialogue("00004", "Bob", "Bye", links(&["00003"])));
+        assert_eq!(
+            problems(&nodes),
+            ["warning in node '00004': can't be reached from any start"]
+        );
+        // Without starts anything nothing goes to counts as one
+        nodes.remove(0);
+        assert_eq!(problems(&nodes), Vec::<String>::new());
+    }
+
+    #[test]
+    fn errors_come_before_warnings() {
+        let nodes = vec![
+            Node::new_start("00001", "intro", links(&["00002"])),
+            Node::new_dialogue("00002", "", "Hi", links(&["00003"])),
+            Node::new_dialogue("00003", "Bob", "", links(&[""])),
+        ];
+        assert_eq!(
+            problems(&nodes),
+            [
+                "error in node '00003': dialogue is empty",
+                "warning in node '00002': character is empty",
+                "warning in node '00003': the output is not connected, the conversation ends there"
+            ]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+// Variables declared for the whole project, which set variable nodes change while it runs. Unlike
+// flags they have a type, and they always have a value since every one starts at its default.
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VarType {
+    Int,
+    Float,
+    String,
+}
+
+impl VarType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            VarType::Int => "int",
+            VarType::Float => "float",
+            VarType::String => "string",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<VarType> {
+        match name {
+            "int" => Some(VarType::Int),
+            "float" => Some(VarType::Float),
+            "string" => Some(VarType::String),
+            _ => None,
+        }
+    }
+
+    // Int, float, string and back to int, for the editor's type button
+    pub fn next(&self) -> VarType {
+        match self {
+            VarType::Int => VarType::Float,
+            VarType::Float => VarType::String,
+            VarType::String => VarType::Int,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl Value {
+    pub fn var_type(&self) -> VarType {
+        match self {
+            Value::Int(_) => VarType::Int,
+            Value::Float(_) => VarType::Float,
+            Value::String(_) => VarType::String,
+        }
+    }
+
+    pub fn zero(var_type: VarType) -> Value {
+        match var_type {
+            VarType::Int => Value::Int(0),
+            VarType::Float => Value::Float(0.),
+            VarType::String => Value::String("".to_string()),
+        }
+    }
+
+    // Text typed in the editor, as a value of the given type. Strings take the text as it is.
+    pub fn parse(text: &str, var_type: VarType) -> Result<Value, String> {
+        match var_type {
+            VarType::Int => match text.trim().parse() {
+                Ok(val) => Ok(Value::Int(val)),
+                Err(_) => Err(format!("'{}' is not a whole number", text)),
+            },
+            VarType::Float => match text.trim().parse::<f64>() {
+                Ok(val) if val.is_finite() => Ok(Value::Float(val)),
+                _ => Err(format!("'{}' is not a number", text)),
+            },
+            VarType::String => Ok(Value::String(text.to_string())),
+        }
+    }
+}
+
+// Shows values the way they're typed, so parse gives back the same value
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(val) => write!(f, "{}", val),
+            Value::Float(val) => write!(f, "{}", val),
+            Value::String(val) => write!(f, "{}", val),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub default: Value, // also decides the type
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    Assign,
+    Add,
+    Subtract,
+}
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Assign => "assign",
+            Operation::Add => "add",
+            Operation::Subtract => "subtract",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Operation> {
+        match name {
+            "assign" => Some(Operation::Assign),
+            "add" => Some(Operation::Add),
+            "subtract" => Some(Operation::Subtract),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> Operation {
+        match self {
+            Operation::Assign => Operation::Add,
+            Operation::Add => Operation::Subtract,
+            Operation::Subtract => Operation::Assign,
+        }
+    }
+
+    // The new value of a variable, operand has to be of the same type. Integers saturate instead
+    // of overflowing.
+    pub fn apply(&self, current: &Value, operand: &Value) -> Result<Value, String> {
+        match (self, current, operand) {
+            (Operation::Assign, _, _) if current.var_type() == operand.var_type() => {
+                Ok(operand.clone())
+            }
+            (Operation::Add, Value::Int(a), Value::Int(b)) => Ok(Value::Int(a.saturating_add(*b))),
+            (Operation::Add, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
+            (Operation::Subtract, Value::Int(a), Value::Int(b)) => {
+                Ok(Value::Int(a.saturating_sub(*b)))
+            }
+            (Operation::Subtract, Value::Float(a), Value::Float(b)) => Ok(Value::Float(a - b)),
+            (Operation::Add | Operation::Subtract, Value::String(_), _) => {
+                Err("only numbers can be added or subtracted".to_string())
+            }
+            _ => Err(format!(
+                "the value is a {} but the variable is a {}",
+                operand.var_type().name(),
+                current.var_type().name()
+            )),
+        }
+    }
+}